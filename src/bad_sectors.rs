@@ -0,0 +1,103 @@
+//! Tolerating media errors: which sectors could not be written, and what to
+//! do when a write fails.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::ops::Range;
+use std::time::Duration;
+
+/// What a job does when the target reports a media error (`EIO`) on write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadSectorPolicy {
+    /// Stop the job with an I/O error.
+    #[default]
+    Abort,
+    /// Retry the chunk `retries` times, doubling the delay from `backoff`
+    /// each time, then bisect it down to the failing sectors, record them,
+    /// and carry on past them.
+    Skip { retries: u32, backoff: Duration },
+}
+
+impl BadSectorPolicy {
+    /// `Skip` with 3 retries starting 100 ms apart.
+    pub fn skip() -> BadSectorPolicy {
+        BadSectorPolicy::Skip {
+            retries: 3,
+            backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// True for errors that mean the medium could not take the write, as
+/// opposed to the device going away.
+pub(crate) fn is_media_error(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::EIO)
+}
+
+/// The sectors found unwritable so far, as sorted, merged byte ranges.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BadSectors {
+    sector: u64,
+    ranges: Vec<Range<u64>>,
+}
+
+impl BadSectors {
+    /// Starts from LBA ranges recorded earlier, such as in a journal.
+    pub(crate) fn new(sector_size: u32, lbas: &[Range<u64>]) -> BadSectors {
+        let sector = u64::from(sector_size.max(1));
+        let mut bad = BadSectors {
+            sector,
+            ranges: Vec::new(),
+        };
+        for lba in lbas {
+            bad.insert(lba.start * sector..lba.end * sector);
+        }
+        bad
+    }
+
+    /// Marks the bytes in `range` unwritable.
+    pub(crate) fn insert(&mut self, range: Range<u64>) {
+        let at = self.ranges.partition_point(|r| r.end < range.start);
+        let mut merged = range;
+        while at < self.ranges.len() && self.ranges[at].start <= merged.end {
+            let next = self.ranges.remove(at);
+            merged = merged.start.min(next.start)..merged.end.max(next.end);
+        }
+        self.ranges.insert(at, merged);
+    }
+
+    /// Marks everything in `other` unwritable too.
+    pub(crate) fn extend(&mut self, other: &BadSectors) {
+        for range in &other.ranges {
+            self.insert(range.clone());
+        }
+    }
+
+    /// The parts of `range` outside every bad range.
+    pub(crate) fn good_parts(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut parts = Vec::new();
+        let mut start = range.start;
+        for bad in self.ranges.iter().filter(|b| b.end > range.start) {
+            if bad.start >= range.end {
+                break;
+            }
+            if bad.start > start {
+                parts.push(start..bad.start);
+            }
+            start = start.max(bad.end);
+        }
+        if start < range.end {
+            parts.push(start..range.end);
+        }
+        parts
+    }
+
+    /// The bad ranges as logical block addresses, end exclusive.
+    pub(crate) fn lbas(&self) -> Vec<Range<u64>> {
+        self.ranges
+            .iter()
+            .map(|r| r.start / self.sector..r.end.div_ceil(self.sector))
+            .collect()
+    }
+}
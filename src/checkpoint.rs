@@ -0,0 +1,212 @@
+//! On-disk journal that lets an interrupted wipe continue where it stopped
+//! instead of starting over from the first pass.
+
+use crate::bad_sectors::BadSectorPolicy;
+use crate::engine::CHUNK_SIZE;
+use crate::error::{Error, Result};
+use crate::identity::Identity;
+use crate::job::{IoBackend, SparseMode, VerifyPolicy};
+use crate::method::{Pattern, WipeMethod};
+use crate::rng::RunSecret;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Range;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+/// Where `wipers wipe` keeps its journals unless told otherwise.
+pub const DEFAULT_JOURNAL_DIR: &str = "/var/lib/wipers/journal";
+
+// Defaults for journals written before these settings were recorded,
+// matching what `WipeJob::resume` used then.
+fn default_block_size() -> usize {
+    CHUNK_SIZE
+}
+
+fn default_queue_depth() -> usize {
+    1
+}
+
+fn default_direct_io() -> bool {
+    true
+}
+
+/// What the current pass is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Write,
+    Verify,
+}
+
+/// Position in a random pass's ChaCha20 keystream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngPosition {
+    pub stream: u64,
+    /// Index of the next 32-bit word.
+    pub word: u64,
+}
+
+/// Where a resumed job picked up, and how often it has been interrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resumed {
+    pub pass: u32,
+    pub phase: Phase,
+    pub offset: u64,
+    pub interruptions: u32,
+}
+
+/// The state of a running wipe, saved each time the target is flushed.
+///
+/// The journal never holds the run secret, only its fingerprint: random
+/// passes left to do can only be resumed with the secret supplied again.
+/// It is still written readable only by its owner, and removed once the
+/// wipe completes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Version of wipers that wrote the journal.
+    pub version: String,
+    /// The target as it was given when the wipe started.
+    pub target: PathBuf,
+    /// The drive being wiped; absent for image files.
+    pub identity: Option<Identity>,
+    pub method: WipeMethod,
+    pub rounds: u32,
+    pub verify: VerifyPolicy,
+    pub sparse: SparseMode,
+    #[serde(default)]
+    pub on_bad_sector: BadSectorPolicy,
+    /// Bytes per write and read.
+    #[serde(default = "default_block_size")]
+    pub block_size: usize,
+    /// Writes kept in flight at once.
+    #[serde(default = "default_queue_depth")]
+    pub queue_depth: usize,
+    #[serde(default)]
+    pub io_backend: IoBackend,
+    /// Passes are written with `O_DIRECT` where the target supports it.
+    #[serde(default = "default_direct_io")]
+    pub direct_io: bool,
+    /// Fingerprint of the run secret, if the method has random passes.
+    #[serde(default)]
+    pub secret_id: Option<String>,
+    /// Byte ranges each pass covers.
+    pub regions: Vec<Range<u64>>,
+    /// The pass in progress, from 1; one past the last once all are done.
+    pub pass: u32,
+    pub passes: u32,
+    pub phase: Phase,
+    /// Everything of the current pass before this offset is durably on the
+    /// target.
+    pub offset: u64,
+    /// Where the random stream of the current pass stands at `offset`.
+    /// `None` while verifying, which always starts over from the beginning
+    /// of the pass.
+    pub rng: Option<RngPosition>,
+    pub bytes_written: u64,
+    pub bytes_verified: u64,
+    /// Some pass has been read back and matched.
+    pub verified: bool,
+    /// Seconds since the Unix epoch at which the wipe first started.
+    pub started: u64,
+    /// How many times the wipe has been resumed so far.
+    pub interruptions: u32,
+    /// Logical sectors found unwritable so far, as end-exclusive LBA ranges.
+    #[serde(default)]
+    pub bad_sectors: Vec<Range<u64>>,
+}
+
+impl Checkpoint {
+    /// Whether a pass still to be written or verified is random, so that
+    /// resuming needs the run secret.
+    pub fn needs_secret(&self) -> bool {
+        let Ok(fills) = self.method.resolve() else {
+            return false;
+        };
+        !fills.is_empty()
+            && (self.pass.max(1) as usize - 1..self.passes as usize)
+                .any(|i| fills[i % fills.len()].pattern == Pattern::Random)
+    }
+
+    /// Whether `secret` is the one the wipe started with, as far as the
+    /// journal can tell.
+    pub fn matches_secret(&self, secret: &RunSecret) -> bool {
+        self.secret_id
+            .as_ref()
+            .is_none_or(|id| *id == secret.fingerprint())
+    }
+
+    /// Share of the whole job that is done, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        let per_pass: u64 = self.regions.iter().map(|r| r.end - r.start).sum();
+        if self.passes == 0 || per_pass == 0 {
+            return 1.0;
+        }
+        let done = u64::from(self.pass.min(self.passes + 1) - 1) * per_pass
+            + match self.phase {
+                Phase::Write => self.offset.min(per_pass),
+                Phase::Verify => per_pass,
+            };
+        (done as f64 / (u64::from(self.passes) * per_pass) as f64).min(1.0)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Checkpoint> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| Error::io(path, "read", e))?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::io(path, "parse", io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Replaces the journal at `path` atomically: the new state is written
+    /// and synced to a temporary file, which is then renamed over it.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("checkpoints always serialize");
+        let tmp = path.with_extension("tmp");
+        let write = || -> io::Result<()> {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&tmp)?;
+            file.write_all(json.as_bytes())?;
+            file.write_all(b"\n")?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)?;
+            if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                File::open(dir)?.sync_all()?;
+            }
+            Ok(())
+        };
+        write().map_err(|e| Error::io(path, "write the journal", e))
+    }
+
+    /// The journal file for `target` in `dir`, named after the drive's
+    /// stable link so that it survives the drive being renumbered.
+    pub fn path_in(dir: &Path, target: &Path, identity: Option<&Identity>) -> PathBuf {
+        let path = identity.map_or(target, |id| &id.stable_path);
+        let name = match identity {
+            Some(_) => path.file_name().map_or_else(
+                || path.to_string_lossy().into_owned(),
+                |n| n.to_string_lossy().into_owned(),
+            ),
+            None => {
+                let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+                path.to_string_lossy()
+                    .trim_start_matches('/')
+                    .replace('/', "!")
+            }
+        };
+        dir.join(format!("{}.json", name))
+    }
+}
+
+/// Removes a journal once its wipe has completed.
+pub(crate) fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(Error::io(path, "remove the journal", e)),
+    }
+}
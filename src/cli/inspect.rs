@@ -0,0 +1,65 @@
+use super::{human_size, or_exit, parse_target};
+use clap::Args;
+use std::fs::File;
+use std::path::PathBuf;
+use wipers::{DevId, SysRoot};
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Block device or image file to inspect
+    #[arg(value_name = "TARGET", value_parser = parse_target)]
+    target: PathBuf,
+}
+
+pub fn run(args: InspectArgs) -> i32 {
+    let target = &args.target;
+    let file = match File::open(target) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error: cannot open {}: {}", target.display(), e);
+            return 1;
+        }
+    };
+    let geometry = or_exit(wipers::geometry(&file, target));
+
+    println!("Target:          {}", target.display());
+    if let Ok(dev) = DevId::of(target) {
+        println!("Device number:   {}", dev);
+    }
+    println!(
+        "Size:            {} ({} bytes)",
+        human_size(geometry.size),
+        geometry.size
+    );
+    println!("Logical sector:  {}", geometry.logical_sector_size);
+    println!("Physical sector: {}", geometry.physical_sector_size);
+
+    let drive = or_exit(SysRoot::default().drive_for(target));
+    if let Some(drive) = &drive {
+        println!("Model:           {}", drive.model.as_deref().unwrap_or("-"));
+        println!(
+            "Serial:          {}",
+            drive.serial.as_deref().unwrap_or("-")
+        );
+        println!("WWN:             {}", drive.wwn.as_deref().unwrap_or("-"));
+        println!("Transport:       {}", drive.transport);
+        println!("Rotational:      {}", drive.rotational);
+        println!("Removable:       {}", drive.removable);
+        println!("Partitions:      {}", drive.partitions.join(", "));
+    }
+
+    let blockers = drive.map(|d| d.blockers).unwrap_or_default();
+    let holders = or_exit(wipers::open_holders(target));
+    if blockers.is_empty() && holders.is_empty() {
+        println!("In use:          no");
+    } else {
+        println!("In use:");
+        for blocker in &blockers {
+            println!("  {}", blocker);
+        }
+        for holder in &holders {
+            println!("  open by {} (pid {})", holder.command, holder.pid);
+        }
+    }
+    0
+}
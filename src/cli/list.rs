@@ -0,0 +1,64 @@
+use super::{human_size, or_exit};
+use clap::Args;
+use wipers::{Drive, SysRoot, SystemDisk};
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Print the inventory as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+fn status(drive: &Drive, system: &[SystemDisk]) -> String {
+    if system.iter().any(|d| d.dev == drive.dev) {
+        "system".to_string()
+    } else if drive.is_idle() {
+        "idle".to_string()
+    } else if drive.is_mounted() {
+        "mounted".to_string()
+    } else {
+        "in use".to_string()
+    }
+}
+
+pub fn run(args: ListArgs) -> i32 {
+    let sys = SysRoot::default();
+    let drives = or_exit(sys.drives());
+    let system = or_exit(sys.system_disks());
+
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&drives).expect("drives always serialize")
+        );
+        return 0;
+    }
+
+    println!(
+        "{:<10} {:>10} {:>9} {:<7} {:<4} {:<3} {:<24} {:<20} {:<20} {:<5} STATUS",
+        "NAME", "SIZE", "SECTORS", "TRAN", "ROTA", "RM", "MODEL", "SERIAL", "WWN", "PARTS"
+    );
+    for drive in &drives {
+        println!(
+            "{:<10} {:>10} {:>9} {:<7} {:<4} {:<3} {:<24} {:<20} {:<20} {:<5} {}",
+            drive.name,
+            human_size(drive.size),
+            format!(
+                "{}/{}",
+                drive.logical_sector_size, drive.physical_sector_size
+            ),
+            drive.transport,
+            if drive.rotational { "yes" } else { "no" },
+            if drive.removable { "yes" } else { "no" },
+            drive.model.as_deref().unwrap_or("-"),
+            drive.serial.as_deref().unwrap_or("-"),
+            drive.wwn.as_deref().unwrap_or("-"),
+            drive.partitions.len(),
+            status(drive, &system)
+        );
+        for blocker in &drive.blockers {
+            println!("{:<10} {}", "", blocker);
+        }
+    }
+    0
+}
@@ -0,0 +1,328 @@
+//! Command-line interface: argument definitions and shared helpers.
+
+pub mod inspect;
+pub mod list;
+pub mod report;
+pub mod resume;
+pub mod verify;
+pub mod wipe;
+
+use clap::{Args, Parser, Subcommand};
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use wipers::{Drive, Event, RunSecret, Sample, Selector, SysRoot, WipeMethod, STANDARDS};
+
+#[derive(Parser)]
+#[command(name = "wipers", version, about = "Securely wipe block devices")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Overwrite one or more targets
+    Wipe(wipe::WipeArgs),
+    /// Check that targets still hold the final pass of a method, without writing
+    Verify(verify::VerifyArgs),
+    /// List block devices
+    List(list::ListArgs),
+    /// Show a target's geometry and everything that is using it
+    Inspect(inspect::InspectArgs),
+    /// Print a report saved by `wipe --report`
+    Report(report::ReportArgs),
+    /// Continue interrupted wipes from their checkpoint journals
+    Resume(resume::ResumeArgs),
+}
+
+/// How the data of each pass is chosen.
+#[derive(Args)]
+pub struct MethodArgs {
+    /// Built-in method: zero, random, dod, gutmann, schneier, vsitr,
+    /// hmg-is5-baseline, hmg-is5-enhanced or rcmp-tssit-ops-ii
+    #[arg(long, value_name = "NAME", value_parser = parse_standard)]
+    method: Option<WipeMethod>,
+
+    /// Load a custom method from a TOML file
+    #[arg(long, value_name = "PATH", value_parser = parse_method_file, conflicts_with = "method")]
+    method_file: Option<WipeMethod>,
+
+    /// Number of times to repeat the whole method
+    #[arg(long, value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub passes: u32,
+
+    /// Key random passes with a secret previously saved by --export-seed
+    #[arg(long, value_name = "PATH", value_parser = parse_seed_file)]
+    seed_file: Option<RunSecret>,
+}
+
+impl MethodArgs {
+    pub fn method(&self) -> WipeMethod {
+        self.method
+            .clone()
+            .or_else(|| self.method_file.clone())
+            .unwrap_or_else(WipeMethod::zero)
+    }
+
+    /// The secret from --seed-file, or a fresh one.
+    pub fn secret(&self) -> RunSecret {
+        self.seed_file.clone().unwrap_or_else(RunSecret::generate)
+    }
+
+    pub fn has_seed_file(&self) -> bool {
+        self.seed_file.is_some()
+    }
+}
+
+fn parse_standard(name: &str) -> Result<WipeMethod, String> {
+    WipeMethod::standard(name).map_err(|_| {
+        let known: Vec<_> = STANDARDS.iter().map(|s| s.id).collect();
+        format!("expected one of: {}", known.join(", "))
+    })
+}
+
+fn parse_method_file(path: &str) -> Result<WipeMethod, String> {
+    WipeMethod::load(path).map_err(|e| e.to_string())
+}
+
+fn parse_seed_file(path: &str) -> Result<RunSecret, String> {
+    let hex = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    RunSecret::from_hex(&hex).map_err(|e| e.to_string())
+}
+
+/// Accepts only paths that resolve to a block device or a regular file.
+pub fn parse_target(path: &str) -> Result<PathBuf, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let file_type = metadata.file_type();
+    if file_type.is_block_device() || file_type.is_file() {
+        Ok(PathBuf::from(path))
+    } else {
+        Err("not a block device or regular file".to_string())
+    }
+}
+
+/// Parses a byte count with an optional K, M or G suffix (powers of 1024).
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 30),
+        _ => (s, 0),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("'{}' is not a size such as 4096, 256K or 1M", s))
+}
+
+/// Accepts a selector such as `serial:WD-XXXX`, or a path that resolves to a
+/// block device or a regular file.
+pub fn parse_selector(s: &str) -> Result<Selector, String> {
+    match s.parse::<Selector>().map_err(|e| e.to_string())? {
+        Selector::Path(_) => parse_target(s).map(Selector::Path),
+        selector => Ok(selector),
+    }
+}
+
+/// A concrete target and the drive behind it, if it is a block device.
+pub struct Target {
+    pub path: PathBuf,
+    pub drive: Option<Drive>,
+}
+
+/// Resolves selectors to concrete targets, in order and without duplicates.
+/// Paths are kept as given; other selectors become the drive's `/dev` node.
+/// Selectors matching several drives select them all only with
+/// `all_matching`.
+pub fn resolve_targets(sys: &SysRoot, selectors: &[Selector], all_matching: bool) -> Vec<Target> {
+    let mut targets: Vec<Target> = Vec::new();
+    for selector in selectors {
+        let found = match selector {
+            Selector::Path(path) => vec![Target {
+                path: path.clone(),
+                drive: or_exit(sys.drive_for(path)),
+            }],
+            _ => or_exit(sys.select(selector, all_matching))
+                .into_iter()
+                .map(|drive| Target {
+                    path: drive.path.clone(),
+                    drive: Some(drive),
+                })
+                .collect(),
+        };
+        for target in found {
+            let duplicate = targets.iter().any(|t| match (&t.drive, &target.drive) {
+                (Some(a), Some(b)) => a.dev == b.dev,
+                _ => t.path == target.path,
+            });
+            if !duplicate {
+                targets.push(target);
+            }
+        }
+    }
+    targets
+}
+
+/// Prints the concrete targets, if any selector needed resolving.
+/// Returns whether anything was printed.
+pub fn print_resolved(selectors: &[Selector], targets: &[Target]) -> bool {
+    if selectors.iter().all(|s| matches!(s, Selector::Path(_))) {
+        return false;
+    }
+    println!("Selected targets:");
+    for target in targets {
+        match &target.drive {
+            Some(drive) => println!(
+                "  {:<12} {:>10}  {:<6} {:<24} {}",
+                target.path.display(),
+                human_size(drive.size),
+                drive.transport,
+                drive.model.as_deref().unwrap_or("-"),
+                drive.serial.as_deref().unwrap_or("-")
+            ),
+            None => println!("  {}", target.path.display()),
+        }
+    }
+    true
+}
+
+/// Asks a yes/no question on the terminal.
+pub fn confirm(question: &str) -> io::Result<bool> {
+    print!("{} (y/n): ", question);
+    io::stdout().flush()?;
+
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    Ok(response.trim().eq_ignore_ascii_case("y"))
+}
+
+pub fn print_method(method: &WipeMethod, rounds: u32) {
+    println!("Method: {} ({} passes)", method.name, method.passes.len());
+    for (n, pass) in method.passes.iter().enumerate() {
+        println!("  {:>2}: {}", n + 1, pass);
+    }
+    if rounds > 1 {
+        println!("Repeated {} times", rounds);
+    }
+}
+
+pub fn print_event(device: &Path, event: &Event) {
+    let device = device.display();
+    match event {
+        Event::PassStarted {
+            pass,
+            passes,
+            pattern,
+        } => println!("Pass {} of {} on {} ({})", pass, passes, device, pattern),
+        Event::Progress { written, total, .. } => {
+            let progress = (*written as f64 / *total as f64) * 100.0;
+            print!("\rProgress: {:.2}%", progress);
+            let _ = io::stdout().flush();
+        }
+        Event::PassFinished { pass } => println!("\nPass {} complete.", pass),
+        Event::VerifyStarted { pass } => println!("Verifying pass {} on {}", pass, device),
+        Event::VerifyProgress { .. } => {}
+    }
+}
+
+/// Set by the first SIGINT or SIGTERM; the jobs watching it stop at their
+/// next chunk boundary.
+static STOP: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    let Some(stop) = STOP.get() else {
+        return;
+    };
+    if stop.swap(true, Ordering::SeqCst) {
+        // SAFETY: _exit is async-signal-safe.
+        unsafe { libc::_exit(128 + signal) };
+    }
+    let msg = b"\nInterrupted: stopping at the next chunk boundary. Press Ctrl-C again to exit immediately.\n";
+    // SAFETY: write is async-signal-safe and the buffer is static.
+    unsafe { libc::write(libc::STDERR_FILENO, msg.as_ptr().cast(), msg.len()) };
+}
+
+/// Makes SIGINT and SIGTERM stop running jobs cleanly instead of killing
+/// the process mid-write, and returns the flag they set. A second signal
+/// exits immediately.
+pub fn stop_on_signals() -> Arc<AtomicBool> {
+    let stop = STOP
+        .get_or_init(|| Arc::new(AtomicBool::new(false)))
+        .clone();
+    let handler: extern "C" fn(libc::c_int) = on_signal;
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only touches atomics and async-signal-safe calls.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+    stop
+}
+
+pub fn or_exit<T>(result: wipers::Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    })
+}
+
+/// Formats a duration as hours and minutes, or seconds when short.
+pub fn human_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Describes a verification sample, such as `a 5.0% sample of 12 regions (seed 42)`.
+pub fn describe_sample(sample: &Sample) -> String {
+    format!(
+        "a {:.1}% sample of {} regions (seed {})",
+        sample.coverage(),
+        sample.regions.len(),
+        sample.sampling.seed()
+    )
+}
+
+/// Formats LBA ranges as `LBA 8, LBA 100-103`, ends inclusive.
+pub fn lba_ranges(ranges: &[Range<u64>]) -> String {
+    ranges
+        .iter()
+        .map(|r| match r.end - r.start {
+            1 => format!("LBA {}", r.start),
+            _ => format!("LBA {}-{}", r.start, r.end - 1),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats a byte count with a binary unit.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
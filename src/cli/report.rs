@@ -0,0 +1,72 @@
+use super::{describe_sample, human_size, lba_ranges, or_exit};
+use clap::Args;
+use std::path::PathBuf;
+use wipers::Report;
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Report file written by `wipers wipe --report`
+    #[arg(value_name = "PATH")]
+    path: PathBuf,
+}
+
+pub fn run(args: ReportArgs) -> i32 {
+    let report = or_exit(Report::load(&args.path));
+
+    println!("wipers {} report", report.version);
+    if report.dry_run {
+        println!("DRY RUN: nothing was written to any target");
+    }
+    println!("Started:  {} (Unix time)", report.started);
+    println!("Finished: {} (Unix time)", report.finished);
+    println!(
+        "Method:   {} ({} passes, {} rounds)",
+        report.method,
+        report.passes.len(),
+        report.rounds
+    );
+    for (n, pass) in report.passes.iter().enumerate() {
+        println!("  {:>2}: {}", n + 1, pass);
+    }
+
+    println!("Devices:");
+    for device in &report.devices {
+        print!("  {}: {}", device.target.display(), device.status);
+        if let Some(size) = device.size {
+            print!(", {}", human_size(size));
+        }
+        match (&device.sample, device.verified) {
+            (Some(sample), true) => print!(", verified {}", describe_sample(sample)),
+            (None, true) => print!(", verified"),
+            (_, false) => {}
+        }
+        if let Some(resumed) = &device.resumed {
+            print!(
+                ", interrupted {} time(s), last resumed at pass {} offset {}",
+                resumed.interruptions, resumed.pass, resumed.offset
+            );
+        }
+        if !device.bad_sectors.is_empty() {
+            print!(", unwritable: {}", lba_ranges(&device.bad_sectors));
+        }
+        match &device.error {
+            Some(error) => println!(" ({})", error),
+            None => println!(),
+        }
+        if let Some(sample) = device.sample.as_ref().filter(|_| device.verified) {
+            for region in &sample.regions {
+                println!("    checked {}..{}", region.start, region.end);
+            }
+        }
+    }
+
+    if report
+        .devices
+        .iter()
+        .all(|d| matches!(d.status.as_str(), "wiped" | "dry-run"))
+    {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,184 @@
+use super::wipe::{allowed, ensure_idle, execute, status_code, Run};
+use super::{confirm, human_size, or_exit, parse_seed_file, print_method};
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+use wipers::{
+    Checkpoint, JobStatus, Phase, Policy, Report, RunSecret, SysRoot, WipeJob, DEFAULT_JOURNAL_DIR,
+};
+
+#[derive(Args)]
+pub struct ResumeArgs {
+    /// Where `wipe` kept its journals
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_JOURNAL_DIR)]
+    journal_dir: PathBuf,
+
+    /// Write a JSON report of the run
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+
+    /// Allow and deny rules for drives [default: /etc/wipers/policy.toml if present]
+    #[arg(long, value_name = "PATH")]
+    policy: Option<PathBuf>,
+
+    /// Resume wiping this disk, or a partition of it, even though the
+    /// running system depends on it; give the disk's kernel name, such as sda
+    #[arg(long, value_name = "NAME")]
+    destroy_system_disk: Vec<String>,
+
+    /// The secret saved by `wipe --export-seed` or given to it as
+    /// --seed-file; needed to resume random passes, which the journal
+    /// cannot continue on its own
+    #[arg(long, value_name = "PATH", value_parser = parse_seed_file)]
+    seed_file: Option<RunSecret>,
+
+    /// Skip the confirmation; only accepted when the policy file sets
+    /// unattended = true
+    #[arg(long)]
+    yes: bool,
+
+    /// Journals to resume [default: every journal in the journal directory]
+    #[arg(value_name = "JOURNAL")]
+    journals: Vec<PathBuf>,
+}
+
+/// Every journal in `dir`, in name order.
+fn journals_in(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut journals: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    journals.sort();
+    journals
+}
+
+pub fn run(args: ResumeArgs) -> i32 {
+    let paths = if args.journals.is_empty() {
+        journals_in(&args.journal_dir)
+    } else {
+        args.journals.clone()
+    };
+    if paths.is_empty() {
+        println!("No unfinished wipes in {}.", args.journal_dir.display());
+        return 0;
+    }
+
+    let policy = or_exit(match &args.policy {
+        Some(path) => Policy::load(path),
+        None => Policy::load_default(),
+    });
+    if args.yes && !policy.unattended {
+        eprintln!("Error: --yes is only accepted when the policy file sets unattended = true");
+        return 1;
+    }
+
+    let checkpoints: Vec<Checkpoint> = paths.iter().map(|p| or_exit(Checkpoint::load(p))).collect();
+    let first = &checkpoints[0];
+    if checkpoints
+        .iter()
+        .any(|c| c.method != first.method || c.rounds != first.rounds)
+    {
+        eprintln!("Error: these wipes use different methods; resume them one at a time");
+        return 1;
+    }
+
+    // Find every drive again, possibly under a new name, before touching any
+    let sys = SysRoot::default();
+    let system = or_exit(sys.system_disks());
+    println!("Unfinished wipes:");
+    let mut runs = Vec::new();
+    for (checkpoint, journal) in checkpoints.iter().zip(&paths) {
+        let (path, drive) = match &checkpoint.identity {
+            Some(identity) => {
+                let now = match identity.relocate(&sys) {
+                    Ok(now) => now,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        return status_code(Some(&JobStatus::IdentityChanged(e)));
+                    }
+                };
+                let drive = or_exit(sys.drive(&now.name));
+                (drive.path.clone(), Some(drive))
+            }
+            None => (checkpoint.target.clone(), None),
+        };
+        println!(
+            "  {} ({}{}): {} pass {} of {}, {:.1}% done, interrupted {} time(s) before",
+            checkpoint.target.display(),
+            path.display(),
+            drive
+                .as_ref()
+                .and_then(|d| d.serial.as_deref())
+                .map_or(String::new(), |serial| format!(", serial {}", serial)),
+            match checkpoint.phase {
+                Phase::Write => "writing",
+                Phase::Verify => "verifying",
+            },
+            checkpoint.pass.min(checkpoint.passes),
+            checkpoint.passes,
+            checkpoint.progress() * 100.0,
+            checkpoint.interruptions
+        );
+        println!(
+            "    {} per pass, journal {}",
+            human_size(checkpoint.regions.iter().map(|r| r.end - r.start).sum()),
+            journal.display()
+        );
+        // The drive may have come back as something the policy now refuses
+        if let Some(drive) = &drive {
+            if !allowed(&policy, drive, &system, &args.destroy_system_disk) {
+                return 1;
+            }
+        }
+        ensure_idle(&path, drive.as_ref(), false);
+        let mut job = or_exit(WipeJob::resume(checkpoint.clone())).journal(journal);
+        if checkpoint.needs_secret() {
+            match &args.seed_file {
+                Some(secret) if checkpoint.matches_secret(secret) => {
+                    job = job.secret(secret.clone())
+                }
+                Some(_) => {
+                    eprintln!(
+                        "Error: --seed-file is not the secret the wipe of {} started with",
+                        checkpoint.target.display()
+                    );
+                    return 1;
+                }
+                None => {
+                    eprintln!(
+                        "Error: the wipe of {} has random passes left, which need its run secret",
+                        checkpoint.target.display()
+                    );
+                    eprintln!("Give the file saved with `wipe --export-seed` as --seed-file.");
+                    return 1;
+                }
+            }
+        }
+        runs.push(Run {
+            job,
+            drive,
+            journal: Some(journal.clone()),
+            estimated_seconds: 0,
+        });
+    }
+
+    if args.yes {
+        println!("\nConfirmation skipped by --yes.");
+    } else if !matches!(confirm("\nContinue wiping these targets?"), Ok(true)) {
+        eprintln!("Nothing was resumed.");
+        return 1;
+    }
+
+    print_method(&first.method, first.rounds);
+    let mut report = Report::new(&first.method, first.rounds);
+    report.started = checkpoints
+        .iter()
+        .map(|c| c.started)
+        .min()
+        .unwrap_or(report.started);
+    execute(runs, report, args.report.as_deref())
+}
@@ -0,0 +1,93 @@
+use super::{
+    describe_sample, or_exit, parse_selector, print_event, print_resolved, resolve_targets,
+    MethodArgs,
+};
+use clap::Args;
+use wipers::{Identity, JobStatus, Sampling, Selector, SysRoot, VerifyPolicy, WipeJob};
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    method: MethodArgs,
+
+    /// Read back only this percentage of each target, in sections picked by
+    /// --sample-seed, always including the first and last
+    #[arg(long, value_name = "PERCENT")]
+    sample: Option<f64>,
+
+    /// Seed picking the sections, as printed by `wipe --verify=sample`
+    /// [default: random]
+    #[arg(long, value_name = "N", requires = "sample")]
+    sample_seed: Option<u64>,
+
+    /// Let a model: or transport: selector that matches several drives
+    /// select all of them, instead of failing as ambiguous
+    #[arg(long)]
+    all_matching: bool,
+
+    /// Block devices, image files, or selectors as accepted by `wipe`
+    #[arg(required = true, value_name = "TARGET", value_parser = parse_selector)]
+    targets: Vec<Selector>,
+}
+
+pub fn run(args: VerifyArgs) -> i32 {
+    let method = args.method.method();
+    if method.ends_with_random() && !args.method.has_seed_file() {
+        eprintln!(
+            "Error: the final pass of '{}' is random; pass the run's --seed-file to verify it",
+            method.name
+        );
+        return 1;
+    }
+
+    let sampling = args.sample.map(|percent| {
+        or_exit(match args.sample_seed {
+            Some(seed) => Sampling::new(percent, seed),
+            None => Sampling::random(percent),
+        })
+    });
+
+    let targets = resolve_targets(&SysRoot::default(), &args.targets, args.all_matching);
+    print_resolved(&args.targets, &targets);
+
+    let mut exit_code = 0;
+    for target in &targets {
+        let mut job = WipeJob::new(&target.path)
+            .method(method.clone())
+            .rounds(args.method.passes)
+            .secret(args.method.secret());
+        if let Some(sampling) = sampling {
+            job = job.verify(VerifyPolicy::Sample(sampling));
+        }
+        if let Some(drive) = &target.drive {
+            job = job.identity(Identity::of(drive));
+        }
+        let target = &target.path;
+        let status = JobStatus::from(job.verify_only(|event| print_event(target, event)));
+        match &status {
+            JobStatus::Wiped(outcome) | JobStatus::CompletedWithBadSectors(outcome) => {
+                match &outcome.sample {
+                    Some(sample) => println!(
+                        "{}: verified {} bytes, {}",
+                        target.display(),
+                        outcome.bytes_verified,
+                        describe_sample(sample)
+                    ),
+                    None => println!(
+                        "{}: verified {} bytes",
+                        target.display(),
+                        outcome.bytes_verified
+                    ),
+                }
+            }
+            JobStatus::VerifyFailed(e)
+            | JobStatus::IoError(e)
+            | JobStatus::Vanished(e)
+            | JobStatus::IdentityChanged(e)
+            | JobStatus::Interrupted(e)
+            | JobStatus::Refused(e) => eprintln!("{}: {}", target.display(), e),
+        }
+        exit_code = exit_code.max(super::wipe::status_code(Some(&status)));
+    }
+    exit_code
+}
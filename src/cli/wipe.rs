@@ -0,0 +1,738 @@
+use super::{
+    confirm, describe_sample, human_duration, human_size, lba_ranges, or_exit, parse_selector,
+    parse_size, print_event, print_method, print_resolved, resolve_targets, stop_on_signals,
+    MethodArgs,
+};
+use clap::{Args, ValueEnum};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use wipers::{
+    BadSectorPolicy, Checkpoint, DeviceReport, Drive, Identity, IoBackend, JobStatus,
+    PartitionTable, Pattern, Policy, Preview, Reason, Report, RunSecret, Sampling, Selector,
+    Signature, SparseMode, SysRoot, SystemDisk, Verification, VerifyPolicy, WipeJob, WipeOutcome,
+    DEFAULT_JOURNAL_DIR, DEFAULT_QUEUE_DEPTH,
+};
+
+#[derive(Args)]
+pub struct WipeArgs {
+    #[command(flatten)]
+    method: MethodArgs,
+
+    /// Read the targets back: last-pass, the default for a bare --verify,
+    /// checks the final pass; full checks every pass; sample:<percent>
+    /// checks that share of the final pass in sections picked by
+    /// --sample-seed, always including the first and last
+    #[arg(long, value_name = "MODE", num_args = 0..=1, require_equals = true,
+          default_missing_value = "last-pass", value_parser = parse_verify)]
+    verify: Option<Verify>,
+
+    /// Seed picking the sections checked by --verify=sample [default: random]
+    #[arg(long, value_name = "N")]
+    sample_seed: Option<u64>,
+
+    /// Save the run secret to a new file so random passes can be verified later
+    #[arg(long, value_name = "PATH")]
+    export_seed: Option<PathBuf>,
+
+    /// Write a JSON report of the run
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+
+    /// Allow and deny rules for drives [default: /etc/wipers/policy.toml if present]
+    #[arg(long, value_name = "PATH")]
+    policy: Option<PathBuf>,
+
+    /// Wipe this disk, or a partition of it, even though the running system
+    /// depends on it; give the disk's kernel name, such as sda
+    #[arg(long, value_name = "NAME")]
+    destroy_system_disk: Vec<String>,
+
+    /// How to overwrite image files: allocate writes the whole file, filling
+    /// its holes; extents overwrites only the allocated extents, then punches
+    /// them out
+    #[arg(long, value_name = "MODE", default_value = "allocate")]
+    sparse: Sparse,
+
+    /// Run every step, including discovery, safety checks and the report,
+    /// but write nothing to the targets
+    #[arg(long)]
+    dry_run: bool,
+
+    /// What to do when a sector cannot be written: abort the wipe, or retry,
+    /// narrow the failure down to single sectors, skip them and carry on
+    #[arg(long, value_name = "ACTION", default_value = "abort")]
+    on_bad_sector: OnBadSector,
+
+    /// Retries of a failed write before it is narrowed down, with --on-bad-sector skip
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    retries: u32,
+
+    /// Bytes per write, such as 256K or 4M, up to 64M; rounded up to the
+    /// sector size
+    #[arg(long, value_name = "SIZE", default_value = "1M", value_parser = parse_block_size)]
+    block_size: usize,
+
+    /// Writes kept in flight at once on each target, each by a writer
+    /// thread without --io-uring; block size times queue depth may be at
+    /// most 256M
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_QUEUE_DEPTH as u32,
+          value_parser = clap::value_parser!(u32).range(1..=64))]
+    queue_depth: u32,
+
+    /// Write through the page cache instead of with O_DIRECT
+    #[arg(long)]
+    buffered: bool,
+
+    /// Submit writes and verification reads through io_uring, from buffers
+    /// registered with the kernel; blocking I/O is used where it is not
+    /// available
+    #[arg(long)]
+    io_uring: bool,
+
+    /// Skip the typed confirmation; only accepted when the policy file sets
+    /// unattended = true
+    #[arg(long)]
+    yes: bool,
+
+    /// Keep checkpoint journals here, so that `wipers resume` can continue
+    /// an interrupted wipe
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_JOURNAL_DIR)]
+    journal_dir: PathBuf,
+
+    /// Let a model: or transport: selector that matches several drives
+    /// select all of them, instead of failing as ambiguous
+    #[arg(long)]
+    all_matching: bool,
+
+    /// Block devices, image files, or selectors: serial:<serial>, wwn:<wwn>,
+    /// by-id:<link>, model:<glob> or transport:<bus>
+    #[arg(required = true, value_name = "TARGET", value_parser = parse_selector)]
+    targets: Vec<Selector>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Sparse {
+    Allocate,
+    Extents,
+}
+
+impl From<Sparse> for SparseMode {
+    fn from(sparse: Sparse) -> Self {
+        match sparse {
+            Sparse::Allocate => SparseMode::Allocate,
+            Sparse::Extents => SparseMode::Extents,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Verify {
+    LastPass,
+    Full,
+    Sample(f64),
+}
+
+fn parse_verify(s: &str) -> Result<Verify, String> {
+    match s {
+        "last-pass" => Ok(Verify::LastPass),
+        "full" => Ok(Verify::Full),
+        _ => {
+            let percent = s
+                .strip_prefix("sample:")
+                .ok_or("expected last-pass, full or sample:<percent>")?;
+            let percent = percent.trim_end_matches('%');
+            match percent.parse::<f64>() {
+                Ok(p) if p > 0.0 && p <= 100.0 => Ok(Verify::Sample(p)),
+                _ => Err(format!(
+                    "'{}' is not a percentage above 0 and at most 100",
+                    percent
+                )),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OnBadSector {
+    Abort,
+    Skip,
+}
+
+/// Largest --block-size accepted.
+const MAX_BLOCK_SIZE: usize = 64 << 20;
+
+/// Most buffer memory a target's writes may take, block size times queue
+/// depth.
+const MAX_BUFFERS: usize = 256 << 20;
+
+fn parse_block_size(s: &str) -> Result<usize, String> {
+    match parse_size(s)? {
+        size if size > MAX_BLOCK_SIZE => Err(format!(
+            "'{}' is larger than the largest block size, {}",
+            s,
+            human_size(MAX_BLOCK_SIZE as u64)
+        )),
+        size => Ok(size),
+    }
+}
+
+/// Delay before the first retry of a failed write; it doubles each time.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Assumed write speed of image files, in bytes per second.
+const IMAGE_THROUGHPUT: u64 = 500_000_000;
+
+/// Writes the run secret to a new file readable only by its owner.
+fn write_secret(path: &Path, secret: &RunSecret) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    writeln!(file, "{}", secret.to_hex())
+}
+
+/// Prints everything about to be destroyed on one target.
+fn print_preview(preview: &Preview) {
+    let contents = |signature: &Option<Signature>| match signature {
+        Some(Signature {
+            kind,
+            label: Some(label),
+        }) => format!("{} \"{}\"", kind, label),
+        Some(Signature { kind, label: None }) => kind.clone(),
+        None => "no filesystem found".to_string(),
+    };
+    let held = |holders: &[String]| match holders {
+        [] => String::new(),
+        _ => format!(", held by {}", holders.join(", ")),
+    };
+
+    println!();
+    match &preview.drive {
+        Some(drive) => {
+            println!(
+                "{}  {}  {}  {}",
+                preview.target.display(),
+                drive.model.as_deref().unwrap_or("unknown model"),
+                human_size(preview.size),
+                drive.transport
+            );
+            println!(
+                "  serial {}, WWN {}",
+                drive.serial.as_deref().unwrap_or("-"),
+                drive.wwn.as_deref().unwrap_or("-")
+            );
+        }
+        None => println!(
+            "{}  image file  {}",
+            preview.target.display(),
+            human_size(preview.size)
+        ),
+    }
+    match preview.table {
+        Some(PartitionTable::Gpt) => println!("  partition table: GPT"),
+        Some(PartitionTable::Mbr) => println!("  partition table: MBR"),
+        None => println!("  partition table: none"),
+    }
+    if preview.signature.is_some() || preview.partitions.is_empty() {
+        println!(
+            "  whole device: {}{}",
+            contents(&preview.signature),
+            held(&preview.holders)
+        );
+    }
+    for part in &preview.partitions {
+        println!(
+            "  {:<12} {:>10}  {}{}",
+            part.name,
+            human_size(part.size),
+            contents(&part.signature),
+            held(&part.holders)
+        );
+    }
+}
+
+/// Makes the operator type the number of targets, or the last characters
+/// of every serial, before anything is destroyed.
+fn confirm_destruction(previews: &[Preview]) -> io::Result<bool> {
+    const SERIAL_DIGITS: usize = 4;
+    let mut suffixes: Option<Vec<String>> = previews
+        .iter()
+        .map(|p| {
+            let serial = p.drive.as_ref()?.serial.as_deref()?;
+            let start = serial.len().saturating_sub(SERIAL_DIGITS);
+            Some(serial.get(start..).unwrap_or(serial).to_ascii_lowercase())
+        })
+        .collect();
+
+    println!(
+        "\nALL DATA on the {} target(s) above will be destroyed.",
+        previews.len()
+    );
+    if suffixes.is_some() {
+        print!(
+            "Type {} or the last {} characters of each serial to continue: ",
+            previews.len(),
+            SERIAL_DIGITS
+        );
+    } else {
+        print!("Type {} to continue: ", previews.len());
+    }
+    io::stdout().flush()?;
+
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    let response = response.trim();
+    if response == previews.len().to_string() {
+        return Ok(true);
+    }
+    let mut typed: Vec<String> = response
+        .split_whitespace()
+        .map(str::to_ascii_lowercase)
+        .collect();
+    typed.sort();
+    Ok(suffixes.as_mut().is_some_and(|suffixes| {
+        suffixes.sort();
+        *suffixes == typed
+    }))
+}
+
+/// Creates the journal directory, or explains that the wipe will not be
+/// resumable if that fails.
+fn journal_dir(dir: &Path) -> Option<&Path> {
+    match fs::create_dir_all(dir) {
+        Ok(()) => Some(dir),
+        Err(e) => {
+            eprintln!(
+                "Warning: cannot keep journals in {}: {}; an interrupted wipe will have to start over",
+                dir.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Makes sure nothing is using `device`, offering to unmount plain mounts
+/// unless this is a dry run.
+/// Checks `drive` against the policy and the system disks, explaining any
+/// refusal.
+pub(super) fn allowed(
+    policy: &Policy,
+    drive: &Drive,
+    system: &[SystemDisk],
+    overrides: &[String],
+) -> bool {
+    let Err(e) = policy.check(drive, system, overrides) else {
+        return true;
+    };
+    eprintln!("Error: {}", e);
+    if system.iter().any(|d| d.dev == drive.disk_dev()) {
+        eprintln!(
+            "If you really mean to destroy it, add --destroy-system-disk {}",
+            drive.disk_name()
+        );
+    }
+    false
+}
+
+pub(super) fn ensure_idle(device: &Path, drive: Option<&Drive>, dry_run: bool) {
+    let holders = or_exit(wipers::open_holders(device));
+    if !holders.is_empty() {
+        eprintln!("The drive {} is in use by:", device.display());
+        for holder in &holders {
+            eprintln!("  {} (pid {})", holder.command, holder.pid);
+        }
+        eprintln!("Please stop these processes and try again.");
+        process::exit(1);
+    }
+
+    let blockers = match drive {
+        Some(drive) if !drive.is_idle() => &drive.blockers,
+        _ => return,
+    };
+    println!("The drive {} is currently in use:", device.display());
+    for blocker in blockers {
+        println!("  {}", blocker);
+    }
+    if blockers
+        .iter()
+        .any(|b| !matches!(b.reason, Reason::Mounted { .. }))
+    {
+        eprintln!("Please release the swap and stacked devices and try again.");
+        process::exit(1);
+    }
+    if dry_run {
+        eprintln!("Dry run: the drive would have to be unmounted first.");
+        process::exit(1);
+    }
+    match confirm("Would you like to unmount the drive now?") {
+        Ok(true) => {
+            or_exit(wipers::unmount_drive(device));
+            println!("Drive {} unmounted successfully.", device.display());
+        }
+        Ok(false) => {
+            eprintln!("Please unmount the drive manually and try again.");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+pub fn run(args: WipeArgs) -> i32 {
+    let method = args.method.method();
+    let rounds = args.method.passes;
+    let secret = args.method.secret();
+    let verify = match args.verify {
+        None => VerifyPolicy::Never,
+        Some(Verify::LastPass) => VerifyPolicy::AfterLastPass,
+        Some(Verify::Full) => VerifyPolicy::EveryPass,
+        Some(Verify::Sample(percent)) => VerifyPolicy::Sample(or_exit(match args.sample_seed {
+            Some(seed) => Sampling::new(percent, seed),
+            None => Sampling::random(percent),
+        })),
+    };
+    let on_bad_sector = match args.on_bad_sector {
+        OnBadSector::Abort => BadSectorPolicy::Abort,
+        OnBadSector::Skip => BadSectorPolicy::Skip {
+            retries: args.retries,
+            backoff: RETRY_BACKOFF,
+        },
+    };
+    if args.block_size * args.queue_depth as usize > MAX_BUFFERS {
+        eprintln!(
+            "Error: --block-size times --queue-depth must be at most {}",
+            human_size(MAX_BUFFERS as u64)
+        );
+        return 1;
+    }
+    let io_backend = if args.io_uring {
+        IoBackend::IoUring
+    } else {
+        IoBackend::Sync
+    };
+
+    if args.dry_run {
+        println!("DRY RUN: nothing will be written to any target.");
+    }
+    if !io_backend.is_available() {
+        eprintln!("io_uring is not available in this build or on this kernel; using blocking I/O.");
+    }
+
+    // Identify each target, then check it is not mounted or in use
+    let sys = SysRoot::default();
+    let targets = resolve_targets(&sys, &args.targets, args.all_matching);
+    let policy = or_exit(match &args.policy {
+        Some(path) => Policy::load(path),
+        None => Policy::load_default(),
+    });
+    if args.yes && !policy.unattended {
+        eprintln!("Error: --yes is only accepted when the policy file sets unattended = true");
+        return 1;
+    }
+    let system = or_exit(sys.system_disks());
+    for drive in targets.iter().filter_map(|t| t.drive.as_ref()) {
+        if !allowed(&policy, drive, &system, &args.destroy_system_disk) {
+            return 1;
+        }
+    }
+    print_resolved(&args.targets, &targets);
+    for target in &targets {
+        ensure_idle(&target.path, target.drive.as_ref(), args.dry_run);
+    }
+
+    let journal_dir = if args.dry_run {
+        None
+    } else {
+        journal_dir(&args.journal_dir)
+    };
+    let random = method.passes.iter().any(|p| p.pattern == Pattern::Random);
+    if journal_dir.is_some() && random && !args.method.has_seed_file() && args.export_seed.is_none()
+    {
+        eprintln!(
+            "The run secret is not saved anywhere, so an interrupted wipe cannot be resumed \
+             through its random passes; use --export-seed to keep it."
+        );
+    }
+    let mut jobs = Vec::new();
+    let mut journals = Vec::new();
+    for target in &targets {
+        let identity = target.drive.as_ref().map(Identity::of);
+        let journal =
+            journal_dir.map(|dir| Checkpoint::path_in(dir, &target.path, identity.as_ref()));
+        if let Some(journal) = journal.as_ref().filter(|j| j.exists()) {
+            eprintln!(
+                "Error: {} holds an unfinished wipe of {}",
+                journal.display(),
+                target.path.display()
+            );
+            eprintln!(
+                "Continue it with `wipers resume {}`, or remove the file to start over.",
+                journal.display()
+            );
+            return 1;
+        }
+        let mut job = WipeJob::new(&target.path)
+            .method(method.clone())
+            .rounds(rounds)
+            .verify(verify)
+            .secret(secret.clone())
+            .sparse(args.sparse.into())
+            .on_bad_sector(on_bad_sector)
+            .block_size(args.block_size)
+            .queue_depth(args.queue_depth as usize)
+            .direct_io(!args.buffered)
+            .io_backend(io_backend)
+            .dry_run(args.dry_run);
+        if let Some(identity) = identity {
+            job = job.identity(identity);
+        }
+        if let Some(journal) = &journal {
+            job = job.journal(journal);
+        }
+        jobs.push(job);
+        journals.push(journal);
+    }
+
+    // Show what is about to be destroyed and make the operator confirm it
+    let previews: Vec<Preview> = targets
+        .iter()
+        .map(|t| or_exit(sys.preview(&t.path, t.drive.as_ref())))
+        .collect();
+    let mut estimates = Vec::new();
+    for (preview, job) in previews.iter().zip(&jobs) {
+        print_preview(preview);
+        let throughput = preview
+            .drive
+            .as_ref()
+            .map_or(IMAGE_THROUGHPUT, Drive::typical_throughput);
+        let estimate = or_exit(job.estimate(preview.size, throughput));
+        println!(
+            "  estimated time: {} ({} written, {} verified)",
+            human_duration(estimate.duration),
+            human_size(estimate.bytes_written),
+            human_size(estimate.bytes_verified)
+        );
+        estimates.push(estimate);
+    }
+    if args.dry_run {
+        println!("\nDry run: no confirmation needed.");
+    } else if args.yes {
+        println!("\nConfirmation skipped by --yes.");
+    } else if !matches!(confirm_destruction(&previews), Ok(true)) {
+        eprintln!("Nothing was wiped.");
+        return 1;
+    }
+
+    if let Some(path) = &args.export_seed {
+        if let Err(e) = write_secret(path, &secret) {
+            eprintln!("Error: cannot export seed to {}: {}", path.display(), e);
+            return 1;
+        }
+        println!("Run seed written to {}", path.display());
+    }
+
+    print_method(&method, rounds);
+    if let VerifyPolicy::Sample(sampling) = verify {
+        println!(
+            "Verifying a {}% sample of each target with seed {}; check the same sample later with --sample {} --sample-seed {}",
+            sampling.percent(),
+            sampling.seed(),
+            sampling.percent(),
+            sampling.seed()
+        );
+    }
+    let mut report = Report::new(&method, rounds);
+    report.dry_run = args.dry_run;
+
+    let runs = jobs
+        .into_iter()
+        .zip(targets)
+        .zip(journals)
+        .zip(&estimates)
+        .map(|(((job, target), journal), estimate)| Run {
+            job,
+            drive: target.drive,
+            journal,
+            estimated_seconds: estimate.duration.as_secs(),
+        })
+        .collect();
+    execute(runs, report, args.report.as_deref())
+}
+
+/// One job to run, with what the report and summary need to know about it.
+pub(super) struct Run {
+    pub job: WipeJob,
+    pub drive: Option<Drive>,
+    pub journal: Option<PathBuf>,
+    pub estimated_seconds: u64,
+}
+
+/// Runs every job on its own thread, then prints the summary and saves the
+/// report. Returns the exit status.
+pub(super) fn execute(runs: Vec<Run>, mut report: Report, report_path: Option<&Path>) -> i32 {
+    let dry_run = report.dry_run;
+    let stop = stop_on_signals();
+    let handles: Vec<_> = runs
+        .into_iter()
+        .map(|run| {
+            let job = run.job.clone().stop_when(stop.clone());
+            let handle = thread::spawn(move || {
+                let status = JobStatus::from(job.run(|event| print_event(job.target(), event)));
+                match &status {
+                    JobStatus::Wiped(_) if dry_run => {
+                        println!("Dry run complete on {}", job.target().display())
+                    }
+                    JobStatus::Wiped(_) => {
+                        println!("Drive wipe complete on {}", job.target().display())
+                    }
+                    JobStatus::CompletedWithBadSectors(_) => println!(
+                        "Drive wipe complete on {}, except for unwritable sectors",
+                        job.target().display()
+                    ),
+                    JobStatus::Interrupted(_) => {
+                        eprintln!("\nStopped {} cleanly", job.target().display())
+                    }
+                    _ => eprintln!("Failed to wipe {}", job.target().display()),
+                }
+                status
+            });
+            (run, handle)
+        })
+        .collect();
+
+    // Wait for all threads, then report every device
+    let results: Vec<(Run, Option<JobStatus>)> = handles
+        .into_iter()
+        .map(|(run, handle)| (run, handle.join().ok()))
+        .collect();
+    report.finish();
+
+    if stop.load(Ordering::Relaxed) {
+        println!("\nSummary (INTERRUPTED):");
+    } else if dry_run {
+        println!("\nSummary (DRY RUN, nothing was written):");
+    } else {
+        println!("\nSummary:");
+    }
+    let mut exit_code = 0;
+    let mut unfinished = Vec::new();
+    for (run, status) in results {
+        let device = run.job.target();
+        println!("  {}: {}", device.display(), describe(status.as_ref()));
+        exit_code = exit_code.max(status_code(status.as_ref()));
+        if let Some(journal) = run.journal.filter(|journal| journal.exists()) {
+            unfinished.push(journal);
+        }
+        if let Some(status) = status {
+            let mut device = DeviceReport::new(device, run.drive, &status);
+            device.estimated_seconds = run.estimated_seconds;
+            report.devices.push(device);
+        }
+    }
+    // Devices that finished before the signal must not hide that the run
+    // was stopped.
+    if stop.load(Ordering::Relaxed) {
+        exit_code = INTERRUPTED;
+    }
+    for journal in &unfinished {
+        println!(
+            "Progress is saved in {}; continue with: wipers resume {}",
+            journal.display(),
+            journal.display()
+        );
+    }
+
+    if let Some(path) = report_path {
+        match report.save(path) {
+            Ok(()) => println!("Report written to {}", path.display()),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                exit_code = exit_code.max(1);
+            }
+        }
+    }
+    exit_code
+}
+
+/// How a completed wipe was verified, for the summary.
+fn verified(outcome: &WipeOutcome) -> String {
+    match (outcome.verification, &outcome.sample) {
+        (Verification::Passed, Some(sample)) => format!(", verified {}", describe_sample(sample)),
+        (Verification::Passed, None) => ", verified".to_string(),
+        (Verification::Skipped | Verification::DryRun, _) => String::new(),
+    }
+}
+
+/// One line per device for the final summary.
+fn describe(status: Option<&JobStatus>) -> String {
+    match status {
+        Some(JobStatus::Wiped(outcome)) if outcome.dry_run => format!(
+            "dry run, {} passes of {} bytes simulated, nothing written{}",
+            outcome.passes,
+            outcome.bytes_per_pass,
+            match outcome.verification {
+                Verification::Skipped => "",
+                _ => ", verification reads simulated",
+            }
+        ),
+        Some(JobStatus::Wiped(outcome)) => format!(
+            "wiped, {} passes of {} bytes ({} written){}{}",
+            outcome.passes,
+            outcome.bytes_per_pass,
+            outcome.bytes_written,
+            verified(outcome),
+            match &outcome.resumed {
+                Some(resumed) => format!(
+                    ", interrupted {} time(s) and resumed at pass {}",
+                    resumed.interruptions, resumed.pass
+                ),
+                None => String::new(),
+            }
+        ),
+        Some(JobStatus::CompletedWithBadSectors(outcome)) => format!(
+            "COMPLETED WITH UNWRITABLE SECTORS: {} passes of {} bytes ({} written){}, could not write {}",
+            outcome.passes,
+            outcome.bytes_per_pass,
+            outcome.bytes_written,
+            verified(outcome),
+            lba_ranges(&outcome.bad_sectors)
+        ),
+        Some(JobStatus::VerifyFailed(e)) => format!("VERIFY FAILED: {}", e),
+        Some(JobStatus::IoError(e)) => format!("I/O ERROR: {}", e),
+        Some(JobStatus::Vanished(e)) => format!("DEVICE VANISHED: {}", e),
+        Some(JobStatus::IdentityChanged(e)) => format!("IDENTITY CHANGED: {}", e),
+        Some(JobStatus::Interrupted(e)) => format!("INTERRUPTED: {}", e),
+        Some(JobStatus::Refused(e)) => format!("REFUSED: {}", e),
+        None => "FAILED: worker thread panicked".to_string(),
+    }
+}
+
+/// Exit status of a run stopped by a signal, whatever became of its
+/// devices.
+const INTERRUPTED: i32 = 8;
+
+/// Exit status contributed by one device; the process exits with the
+/// highest code among all devices, or `INTERRUPTED` if it was stopped.
+pub fn status_code(status: Option<&JobStatus>) -> i32 {
+    match status {
+        Some(JobStatus::Wiped(_)) => 0,
+        Some(JobStatus::Refused(_)) => 2,
+        Some(JobStatus::VerifyFailed(_)) => 3,
+        Some(JobStatus::IoError(_)) => 4,
+        Some(JobStatus::Vanished(_)) => 5,
+        Some(JobStatus::IdentityChanged(_)) => 7,
+        Some(JobStatus::Interrupted(_)) => INTERRUPTED,
+        Some(JobStatus::CompletedWithBadSectors(_)) => 9,
+        None => 6,
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::{Error, Result};
+use crate::sys;
+use std::fs::{self, File};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+
+/// Size and sector layout of a wipe target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    /// Total size in bytes.
+    pub size: u64,
+    pub logical_sector_size: u32,
+    pub physical_sector_size: u32,
+}
+
+/// Queries the geometry of an open block device or regular file.
+///
+/// Regular files report 512-byte logical sectors and their preferred I/O
+/// size as the physical sector size.
+pub fn geometry(file: &File, path: &Path) -> Result<Geometry> {
+    let metadata = file.metadata().map_err(|e| Error::io(path, "stat", e))?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_block_device() {
+        Ok(Geometry {
+            size: sys::device_size(file).map_err(|e| Error::io(path, "query the size of", e))?,
+            logical_sector_size: sys::logical_sector_size(file)
+                .map_err(|e| Error::io(path, "query the logical sector size of", e))?,
+            physical_sector_size: sys::physical_sector_size(file)
+                .map_err(|e| Error::io(path, "query the physical sector size of", e))?,
+        })
+    } else if file_type.is_file() {
+        Ok(Geometry {
+            size: metadata.len(),
+            logical_sector_size: 512,
+            physical_sector_size: metadata.blksize() as u32,
+        })
+    } else {
+        Err(Error::InvalidJob(format!(
+            "{} is not a block device or regular file",
+            path.display()
+        )))
+    }
+}
+
+/// A process holding a file descriptor open on a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Process {
+    pub pid: u32,
+    /// Contents of `/proc/<pid>/comm`.
+    pub command: String,
+}
+
+/// Lists the other processes that hold `device` open, found by scanning
+/// `/proc/*/fd`. Processes whose descriptors cannot be read are skipped.
+pub fn open_holders(device: impl AsRef<Path>) -> Result<Vec<Process>> {
+    let device = device.as_ref();
+    let target = fs::metadata(device).map_err(|e| Error::io(device, "stat", e))?;
+    let same_file = |m: &fs::Metadata| {
+        if target.file_type().is_block_device() {
+            m.file_type().is_block_device() && m.rdev() == target.rdev()
+        } else {
+            m.dev() == target.dev() && m.ino() == target.ino()
+        }
+    };
+
+    let own_pid = std::process::id();
+    let mut holders = Vec::new();
+    let proc_dir = fs::read_dir("/proc").map_err(|e| Error::io("/proc", "read", e))?;
+
+    for entry in proc_dir.flatten() {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            Some(pid) if pid != own_pid => pid,
+            _ => continue,
+        };
+        // Processes exit and hide their descriptors from unprivileged users.
+        let fds = match fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        let holds = fds
+            .flatten()
+            .any(|fd| fs::metadata(fd.path()).is_ok_and(|m| same_file(&m)));
+        if holds {
+            let command = fs::read_to_string(entry.path().join("comm"))
+                .map(|c| c.trim_end().to_string())
+                .unwrap_or_default();
+            holders.push(Process { pid, command });
+        }
+    }
+    Ok(holders)
+}
+
+/// Returns true if any other process holds `device` open.
+pub fn is_drive_in_use(device: impl AsRef<Path>) -> Result<bool> {
+    Ok(!open_holders(device)?.is_empty())
+}
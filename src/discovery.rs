@@ -0,0 +1,302 @@
+//! Inventory of the block devices on the system, read from sysfs.
+
+use crate::error::{Error, Result};
+use crate::sysfs::{DevId, SysRoot};
+use crate::usage::{Blocker, Reason};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The bus a drive is attached through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Sata,
+    Sas,
+    Nvme,
+    Usb,
+    Mmc,
+    Scsi,
+    Virtio,
+    Loop,
+    Unknown,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Transport::Sata => "sata",
+            Transport::Sas => "sas",
+            Transport::Nvme => "nvme",
+            Transport::Usb => "usb",
+            Transport::Mmc => "mmc",
+            Transport::Scsi => "scsi",
+            Transport::Virtio => "virtio",
+            Transport::Loop => "loop",
+            Transport::Unknown => "unknown",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Transport {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Transport, ()> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "sata" | "ata" => Transport::Sata,
+            "sas" => Transport::Sas,
+            "nvme" => Transport::Nvme,
+            "usb" => Transport::Usb,
+            "mmc" => Transport::Mmc,
+            "scsi" => Transport::Scsi,
+            "virtio" => Transport::Virtio,
+            "loop" => Transport::Loop,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// Where a partition lies on its whole disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionOf {
+    /// Kernel name of the disk, such as `sda`.
+    pub disk: String,
+    pub disk_dev: DevId,
+    /// Byte offset of the partition on the disk.
+    pub start: u64,
+}
+
+/// A block device, a whole disk or a partition of one, and what is known
+/// about it. A partition reports the model, serial and WWN of its disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Drive {
+    /// Kernel name, such as `sda`, `sda1` or `nvme0n1`.
+    pub name: String,
+    pub path: PathBuf,
+    pub dev: DevId,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub wwn: Option<String>,
+    pub size: u64,
+    pub logical_sector_size: u32,
+    pub physical_sector_size: u32,
+    pub rotational: bool,
+    pub removable: bool,
+    pub transport: Transport,
+    /// Stable `/dev/disk/by-id` links to the drive.
+    pub by_id: Vec<PathBuf>,
+    /// Kernel names of the partitions.
+    pub partitions: Vec<String>,
+    /// Mounts, swap and stacked devices using the drive or its partitions.
+    pub blockers: Vec<Blocker>,
+    /// The disk the device is a partition of; `None` for a whole disk.
+    #[serde(default)]
+    pub partition_of: Option<PartitionOf>,
+}
+
+impl Drive {
+    /// Device number of the whole disk: the drive's own, or its disk's if
+    /// it is a partition.
+    pub fn disk_dev(&self) -> DevId {
+        self.partition_of.as_ref().map_or(self.dev, |p| p.disk_dev)
+    }
+
+    /// Kernel name of the whole disk.
+    pub fn disk_name(&self) -> &str {
+        self.partition_of.as_ref().map_or(&self.name, |p| &p.disk)
+    }
+
+    pub fn is_mounted(&self) -> bool {
+        self.blockers
+            .iter()
+            .any(|b| matches!(b.reason, Reason::Mounted { .. }))
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.blockers.is_empty()
+    }
+
+    /// A rough sustained sequential write speed for this kind of drive, in
+    /// bytes per second, for estimating how long a wipe takes.
+    pub fn typical_throughput(&self) -> u64 {
+        const MB: u64 = 1_000_000;
+        match self.transport {
+            Transport::Nvme => 1500 * MB,
+            Transport::Usb => 30 * MB,
+            Transport::Mmc => 20 * MB,
+            _ if self.rotational => 150 * MB,
+            _ => 400 * MB,
+        }
+    }
+}
+
+/// Non-empty, trimmed contents of a sysfs attribute.
+fn read_attr(path: &Path) -> Option<String> {
+    let raw = fs::read(path).ok()?;
+    let text = String::from_utf8_lossy(&raw);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The serial number from a SCSI unit serial number VPD page (0x80).
+fn vpd_serial(path: &Path) -> Option<String> {
+    let page = fs::read(path).ok()?;
+    let len = usize::from(*page.get(3)?);
+    let serial = String::from_utf8_lossy(page.get(4..4 + len)?);
+    let serial = serial.trim();
+    (!serial.is_empty()).then(|| serial.to_string())
+}
+
+/// Normalizes a sysfs `wwid` to the `0x…` form printed on drive labels
+/// when it is an NAA or EUI identifier.
+fn normalize_wwn(wwid: &str) -> String {
+    match wwid
+        .strip_prefix("naa.")
+        .or_else(|| wwid.strip_prefix("eui."))
+    {
+        Some(hex) => format!("0x{}", hex.to_ascii_lowercase()),
+        None => wwid.to_string(),
+    }
+}
+
+fn transport_of(name: &str, device_path: &str) -> Transport {
+    if name.starts_with("loop") {
+        Transport::Loop
+    } else if device_path.contains("/usb") {
+        Transport::Usb
+    } else if name.starts_with("nvme") {
+        Transport::Nvme
+    } else if name.starts_with("mmcblk") {
+        Transport::Mmc
+    } else if device_path.contains("/virtio") {
+        Transport::Virtio
+    } else if device_path.contains("/ata") {
+        Transport::Sata
+    } else if device_path.contains("/end_device-") || device_path.contains("/sas_") {
+        Transport::Sas
+    } else if device_path.contains("/host") {
+        Transport::Scsi
+    } else {
+        Transport::Unknown
+    }
+}
+
+impl SysRoot {
+    /// Every whole-disk block device with a medium, sorted by name.
+    pub fn drives(&self) -> Result<Vec<Drive>> {
+        let dir = self.sys_path("block");
+        let entries = fs::read_dir(&dir).map_err(|e| Error::io(&dir, "read", e))?;
+        let mut drives = Vec::new();
+        for entry in entries.flatten() {
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let drive = self.drive(&name)?;
+            if drive.size > 0 {
+                drives.push(drive);
+            }
+        }
+        drives.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(drives)
+    }
+
+    /// Describes the block device `name`: a whole disk from
+    /// `sys/block/<name>`, or a partition from `sys/class/block/<name>`.
+    pub fn drive(&self, name: &str) -> Result<Drive> {
+        let class = self.sys_path("class/block").join(name);
+        if class.join("partition").exists() {
+            return self.partition_drive(name, &class);
+        }
+        let dir = self.sys_path("block").join(name);
+        let dev: DevId = read_attr(&dir.join("dev"))
+            .and_then(|d| d.parse().ok())
+            .ok_or_else(|| Error::InvalidJob(format!("{} is not a block device in sysfs", name)))?;
+        let number = |attr: &str| -> u64 {
+            read_attr(&dir.join(attr))
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0)
+        };
+
+        let device = dir.join("device");
+        let serial = read_attr(&dir.join("serial"))
+            .or_else(|| read_attr(&device.join("serial")))
+            .or_else(|| vpd_serial(&device.join("vpd_pg80")));
+        let wwn = read_attr(&dir.join("wwid"))
+            .or_else(|| read_attr(&device.join("wwid")))
+            .map(|w| normalize_wwn(&w));
+        let device_path = fs::canonicalize(&dir)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Drive {
+            name: name.to_string(),
+            path: Path::new("/dev").join(name),
+            dev,
+            model: read_attr(&device.join("model")),
+            serial,
+            wwn,
+            size: number("size") * 512,
+            logical_sector_size: number("queue/logical_block_size") as u32,
+            physical_sector_size: number("queue/physical_block_size") as u32,
+            rotational: number("queue/rotational") == 1,
+            removable: number("removable") == 1,
+            transport: transport_of(name, &device_path),
+            by_id: self.by_id_links(name),
+            partitions: self.partitions(dev)?.into_iter().map(|p| p.name).collect(),
+            blockers: self.blockers(dev)?,
+            partition_of: None,
+        })
+    }
+
+    /// Describes partition `name`, whose sysfs directory `class` leads into
+    /// that of its disk.
+    fn partition_drive(&self, name: &str, class: &Path) -> Result<Drive> {
+        let dir = fs::canonicalize(class).map_err(|e| Error::io(class, "resolve", e))?;
+        let disk = dir
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| Error::InvalidJob(format!("{} has no disk in sysfs", name)))?;
+        let disk = self.drive(disk)?;
+        let dev: DevId = read_attr(&dir.join("dev"))
+            .and_then(|d| d.parse().ok())
+            .ok_or_else(|| Error::InvalidJob(format!("{} is not a block device in sysfs", name)))?;
+        let sectors = |attr: &str| -> u64 {
+            read_attr(&dir.join(attr))
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0)
+        };
+
+        Ok(Drive {
+            name: name.to_string(),
+            path: Path::new("/dev").join(name),
+            dev,
+            size: sectors("size") * 512,
+            by_id: self.by_id_links(name),
+            partitions: Vec::new(),
+            blockers: self.blockers(dev)?,
+            partition_of: Some(PartitionOf {
+                disk: disk.name.clone(),
+                disk_dev: disk.dev,
+                start: sectors("start") * 512,
+            }),
+            ..disk
+        })
+    }
+
+    /// The drive behind a target path, or `None` for regular files.
+    pub fn drive_for(&self, target: &Path) -> Result<Option<Drive>> {
+        let metadata = fs::metadata(target).map_err(|e| Error::io(target, "stat", e))?;
+        if !metadata.file_type().is_block_device() {
+            return Ok(None);
+        }
+        let dev = DevId::from_raw(metadata.rdev());
+        let name = self.name_of(dev)?;
+        self.drive(&name).map(Some)
+    }
+}
@@ -0,0 +1,637 @@
+//! Writes and verifies exactly the extent of a target, one chunk at a time.
+
+use crate::bad_sectors::{is_media_error, BadSectorPolicy, BadSectors};
+use crate::buffer::AlignedBuf;
+use crate::device::Geometry;
+use crate::error::{Error, Result};
+use crate::job::IoBackend;
+use crate::method::PassData;
+use crate::target::BlockTarget;
+#[cfg(feature = "io-uring")]
+use crate::uring::Ring;
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Amount of data moved per I/O call unless configured otherwise.
+pub(crate) const CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Clone)]
+pub(crate) struct Engine<'a> {
+    path: &'a Path,
+    chunk: usize,
+    /// The larger sector size, of which every chunk is a multiple.
+    unit: usize,
+    /// Chunks written or read at once, each from its own buffer.
+    depth: usize,
+    backend: IoBackend,
+    /// Set once io_uring completes a read or write; shared with clones,
+    /// such as the checker.
+    ring_used: Arc<AtomicBool>,
+    /// Logical sector size, the smallest unit a bad range is narrowed to.
+    sector: u64,
+    /// Byte ranges each pass covers; the whole target unless restricted.
+    regions: Vec<Range<u64>>,
+    dry_run: bool,
+    /// Bytes written between flushes in the middle of a pass.
+    flush_every: Option<u64>,
+    /// Checked at every chunk boundary; once set, the pass stops there.
+    stop: Option<Arc<AtomicBool>>,
+    on_bad_sector: BadSectorPolicy,
+}
+
+impl<'a> Engine<'a> {
+    pub(crate) fn new(path: &'a Path, geometry: Geometry) -> Self {
+        let unit = geometry
+            .physical_sector_size
+            .max(geometry.logical_sector_size)
+            .max(1) as usize;
+        Engine {
+            path,
+            chunk: CHUNK_SIZE.div_ceil(unit) * unit,
+            unit,
+            depth: 1,
+            backend: IoBackend::Sync,
+            ring_used: Arc::default(),
+            sector: u64::from(geometry.logical_sector_size.max(1)),
+            regions: std::iter::once(0..geometry.size).collect(),
+            dry_run: false,
+            flush_every: None,
+            stop: None,
+            on_bad_sector: BadSectorPolicy::Abort,
+        }
+    }
+
+    /// Moves `bytes` per I/O call, rounded up to a whole number of sectors.
+    pub(crate) fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk = bytes.max(1).div_ceil(self.unit) * self.unit;
+        self
+    }
+
+    /// Keeps up to `depth` chunk writes, or reads, in flight at once.
+    pub(crate) fn queue_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Submits reads and writes through `backend` where the target allows.
+    pub(crate) fn io_backend(mut self, backend: IoBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// The backend that has moved data so far: io_uring once the ring has
+    /// completed a read or write, and blocking calls otherwise.
+    pub(crate) fn used_backend(&self) -> IoBackend {
+        match self.ring_used.load(Ordering::Relaxed) {
+            true => IoBackend::IoUring,
+            false => IoBackend::Sync,
+        }
+    }
+
+    /// Restricts every pass to `regions`, such as the allocated extents of
+    /// a sparse file.
+    pub(crate) fn restrict_to(mut self, regions: Vec<Range<u64>>) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    pub(crate) fn regions(&self) -> &[Range<u64>] {
+        &self.regions
+    }
+
+    /// Bytes each pass covers.
+    pub(crate) fn extent(&self) -> u64 {
+        self.regions.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Sends every write to a no-op sink and skips the comparison when
+    /// verifying, while still generating and reading all the data.
+    pub(crate) fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Flushes the target after roughly every `bytes` written, not just at
+    /// the end of each pass.
+    pub(crate) fn flush_every(mut self, bytes: Option<u64>) -> Self {
+        self.flush_every = bytes;
+        self
+    }
+
+    /// Stops a pass at the next chunk boundary once `stop` is set.
+    pub(crate) fn stop_when(mut self, stop: Option<Arc<AtomicBool>>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// What to do when a write fails with a media error.
+    pub(crate) fn on_bad_sector(mut self, policy: BadSectorPolicy) -> Self {
+        self.on_bad_sector = policy;
+        self
+    }
+
+    /// The error for a pass stopped before `offset`. The job fills in the
+    /// pass number.
+    fn interrupted(&self, offset: u64) -> Option<Error> {
+        let stop = self.stop.as_ref()?;
+        stop.load(Ordering::Relaxed).then(|| Error::Interrupted {
+            path: self.path.to_path_buf(),
+            pass: 0,
+            offset,
+        })
+    }
+
+    /// One buffer per chunk in flight, registered with an io_uring instance
+    /// on `target` if that backend was asked for and can be set up.
+    fn queue(&self, target: &dyn BlockTarget) -> Queue {
+        let buffers: Vec<AlignedBuf> = (0..self.depth)
+            .map(|_| AlignedBuf::new(self.chunk))
+            .collect();
+        #[cfg(feature = "io-uring")]
+        if let (IoBackend::IoUring, Some(fd)) = (self.backend, target.raw_fd()) {
+            return match Ring::new(fd, buffers) {
+                Ok(ring) => Queue::Ring(Box::new(ring)),
+                Err(buffers) => Queue::Sync(buffers),
+            };
+        }
+        #[cfg(not(feature = "io-uring"))]
+        let _ = target;
+        Queue::Sync(buffers)
+    }
+
+    /// The chunks of `batch` that have no sectors known to be bad, as
+    /// `(buffer, offset, len)`.
+    fn clean(batch: &[(u64, usize)], bad: &BadSectors) -> Vec<(usize, u64, usize)> {
+        batch
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(offset, len))| {
+                let range = offset..offset + len as u64;
+                bad.good_parts(range.clone()) == [range]
+            })
+            .map(|(i, &(offset, len))| (i, offset, len))
+            .collect()
+    }
+
+    /// Yields `(offset, len)` for every chunk of every region from offset
+    /// `from` on; only the last chunk of a region may be shorter than the
+    /// chunk size.
+    fn chunks(&self, from: u64) -> impl Iterator<Item = (u64, usize)> + '_ {
+        let chunk = self.chunk as u64;
+        self.regions
+            .iter()
+            .filter(move |region| region.end > from)
+            .flat_map(move |region| {
+                let (start, end) = (region.start.max(from), region.end);
+                (0..(end - start).div_ceil(chunk)).map(move |i| {
+                    let offset = start + i * chunk;
+                    (offset, (end - offset).min(chunk) as usize)
+                })
+            })
+    }
+
+    /// Writes one pass over every region from offset `from`, and syncs it
+    /// to the device, keeping up to the queue depth of chunks in flight.
+    /// Through io_uring, chunks are filled and submitted a batch at a time;
+    /// otherwise each buffer gets a writer thread for the pass, which fills
+    /// and writes the next chunk as soon as its previous write completes.
+    /// Sectors already in `bad` are skipped, and any newly found
+    /// unwritable are added to it.
+    /// `on_progress` receives the bytes of the pass covered so far;
+    /// `on_flushed` the offset before which everything is durable, after
+    /// each flush in the middle of the pass, and when the pass is stopped.
+    /// Returns the number of bytes written, or that would have been in a
+    /// dry run. On failure, the error is that of the lowest failing chunk.
+    pub(crate) fn write_pass(
+        &self,
+        target: &dyn BlockTarget,
+        data: &mut PassData,
+        from: u64,
+        bad: &mut BadSectors,
+        mut on_progress: impl FnMut(u64),
+        mut on_flushed: impl FnMut(u64, u64, &BadSectors) -> Result<()>,
+    ) -> Result<u64> {
+        let mut queue = self.queue(target);
+        let writers = match &mut queue {
+            Queue::Sync(buffers) if buffers.len() > 1 && !self.dry_run => mem::take(buffers),
+            _ => Vec::new(),
+        };
+        let mut covered = self
+            .regions
+            .iter()
+            .map(|r| from.clamp(r.start, r.end) - r.start)
+            .sum();
+        let mut written = 0;
+        let mut unflushed = 0;
+        // Chunks are handed out in order, so everything before the lowest
+        // one in flight, or before the end of the last one handed out, is
+        // written. `ahead` holds what was written past that point.
+        let mut in_flight = BTreeSet::new();
+        let mut handed_out = from;
+        let mut ahead: BTreeMap<u64, u64> = BTreeMap::new();
+        let mut failure: Option<(u64, Error)> = None;
+        let mut stopped = None;
+
+        let mut chunks = self.chunks(from).peekable();
+        thread::scope(|scope| {
+            let pool = (!writers.is_empty()).then(|| self.pool(scope, target, data, writers));
+            loop {
+                let mut batch = Vec::new();
+                while failure.is_none() && stopped.is_none() && in_flight.len() < self.depth {
+                    let Some(&(offset, len)) = chunks.peek() else {
+                        break;
+                    };
+                    if let Some(interrupted) = self.interrupted(offset) {
+                        stopped = Some((offset, interrupted));
+                        break;
+                    }
+                    chunks.next();
+                    in_flight.insert(offset);
+                    handed_out = offset + len as u64;
+                    match &pool {
+                        Some(pool) => pool
+                            .work
+                            .send((offset, len, bad.clone()))
+                            .expect("writer threads stopped"),
+                        None => batch.push((offset, len)),
+                    }
+                }
+                if in_flight.is_empty() {
+                    break;
+                }
+
+                let (first, bytes, result) = match &pool {
+                    Some(pool) => {
+                        let (offset, len, result, found) =
+                            pool.done.recv().expect("writer thread panicked");
+                        bad.extend(&found);
+                        in_flight.remove(&offset);
+                        (offset, len as u64, result)
+                    }
+                    None => {
+                        for (&(offset, len), buffer) in batch.iter().zip(queue.buffers_mut()) {
+                            data.fill(offset, &mut buffer[..len]);
+                        }
+                        let bytes = batch.iter().map(|&(_, len)| len as u64).sum();
+                        let result = match self.dry_run {
+                            true => Ok(bytes),
+                            false => self.write_batch(target, &batch, &mut queue, bad),
+                        };
+                        in_flight.clear();
+                        (batch[0].0, bytes, result)
+                    }
+                };
+                let durable = in_flight.first().copied().unwrap_or(handed_out);
+                match result {
+                    Ok(n) => {
+                        written += n;
+                        ahead.insert(first, n);
+                        ahead.retain(|&offset, _| offset >= durable);
+                    }
+                    Err(e) => {
+                        if failure.as_ref().is_none_or(|(at, _)| first < *at) {
+                            failure = Some((first, e));
+                        }
+                        continue;
+                    }
+                }
+                covered += bytes;
+                on_progress(covered);
+
+                unflushed += bytes;
+                if failure.is_none()
+                    && !self.dry_run
+                    && self.flush_every.is_some_and(|every| unflushed >= every)
+                {
+                    target
+                        .flush()
+                        .map_err(|e| Error::io(self.path, "sync", e))?;
+                    on_flushed(durable, written - ahead.values().sum::<u64>(), bad)?;
+                    unflushed = 0;
+                }
+            }
+            Ok(())
+        })?;
+
+        if let Some((_, error)) = failure {
+            return Err(error);
+        }
+        if !self.dry_run {
+            target
+                .flush()
+                .map_err(|e| Error::io(self.path, "sync", e))?;
+        }
+        if let Some((offset, interrupted)) = stopped {
+            if !self.dry_run {
+                on_flushed(offset, written, bad)?;
+            }
+            return Err(interrupted);
+        }
+        Ok(written)
+    }
+
+    /// Starts a writer thread for each of `buffers`, each with its own copy
+    /// of `data`. A writer fills and writes one chunk at a time, and takes
+    /// the next from the pool's bounded queue as soon as its write is done.
+    fn pool<'s>(
+        &'s self,
+        scope: &'s thread::Scope<'s, '_>,
+        target: &'s dyn BlockTarget,
+        data: &PassData<'s>,
+        buffers: Vec<AlignedBuf>,
+    ) -> Pool {
+        let (work, queued) = mpsc::sync_channel(buffers.len());
+        let (finished, done) = mpsc::channel();
+        let queued = Arc::new(Mutex::new(queued));
+        for mut buffer in buffers {
+            let (queued, finished, mut data) = (queued.clone(), finished.clone(), data.clone());
+            scope.spawn(move || loop {
+                // Held only while waiting, so the others keep writing.
+                let next = queued.lock().expect("writer queue poisoned").recv();
+                let Ok((offset, len, mut found)) = next else {
+                    break;
+                };
+                data.fill(offset, &mut buffer[..len]);
+                let result = self.write_chunk(target, &buffer[..len], offset, &mut found);
+                if finished.send((offset, len, result, found)).is_err() {
+                    break;
+                }
+            });
+        }
+        Pool { work, done }
+    }
+
+    /// Writes the chunks of `batch` from the matching buffers of `queue`:
+    /// through the ring at once when there is one, otherwise one after the
+    /// other. Returns the bytes written; on failure, the error of the lowest
+    /// failing chunk.
+    fn write_batch(
+        &self,
+        target: &dyn BlockTarget,
+        batch: &[(u64, usize)],
+        queue: &mut Queue,
+        bad: &mut BadSectors,
+    ) -> Result<u64> {
+        // Chunks the ring did not write in full, and those with bad sectors,
+        // are written again one by one, where the bad-sector policy applies
+        // and errors are reported as without the ring.
+        let done = queue
+            .write(batch.len(), &Engine::clean(batch, bad))
+            .inspect(|done| self.record_ring(done))
+            .unwrap_or_else(|| vec![false; batch.len()]);
+        let mut written = 0;
+        for ((&(offset, len), buffer), done) in batch.iter().zip(queue.buffers()).zip(done) {
+            written += match done {
+                true => len as u64,
+                false => self.write_chunk(target, &buffer[..len], offset, bad)?,
+            };
+        }
+        Ok(written)
+    }
+
+    /// Writes one chunk at `offset`, except the sectors known to be bad.
+    /// Returns the bytes written.
+    fn write_chunk(
+        &self,
+        target: &dyn BlockTarget,
+        buf: &[u8],
+        offset: u64,
+        bad: &mut BadSectors,
+    ) -> Result<u64> {
+        let mut written = 0;
+        for part in bad.good_parts(offset..offset + buf.len() as u64) {
+            let at = (part.start - offset) as usize..(part.end - offset) as usize;
+            written += self.write_tolerant(target, &buf[at], part.start, bad)?;
+        }
+        Ok(written)
+    }
+
+    /// Writes `buf` at `offset`, applying the bad-sector policy if the
+    /// target reports a media error. Returns the bytes actually written.
+    fn write_tolerant(
+        &self,
+        target: &dyn BlockTarget,
+        buf: &[u8],
+        offset: u64,
+        bad: &mut BadSectors,
+    ) -> Result<u64> {
+        let (retries, mut backoff) = match self.on_bad_sector {
+            BadSectorPolicy::Abort => {
+                target
+                    .write_all_at(buf, offset)
+                    .map_err(|e| Error::io_at(self.path, "write", offset, e))?;
+                return Ok(buf.len() as u64);
+            }
+            BadSectorPolicy::Skip { retries, backoff } => (retries, backoff),
+        };
+        for attempt in 0..=retries {
+            match target.write_all_at(buf, offset) {
+                Ok(()) => return Ok(buf.len() as u64),
+                Err(e) if !is_media_error(&e) => {
+                    return Err(Error::io_at(self.path, "write", offset, e))
+                }
+                Err(_) if attempt < retries => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(_) => {}
+            }
+        }
+        self.bisect(target, buf, offset, bad)
+    }
+
+    /// Narrows a failed write down to the sectors that cannot be written by
+    /// halving it, recording those in `bad` and writing the rest. Returns
+    /// the bytes written.
+    fn bisect(
+        &self,
+        target: &dyn BlockTarget,
+        buf: &[u8],
+        offset: u64,
+        bad: &mut BadSectors,
+    ) -> Result<u64> {
+        let len = buf.len() as u64;
+        if len <= self.sector {
+            bad.insert(offset..offset + len);
+            return Ok(0);
+        }
+        let half = ((len / 2) / self.sector).max(1) * self.sector;
+        let (head, tail) = buf.split_at(half as usize);
+        let mut written = 0;
+        for (part, at) in [(head, offset), (tail, offset + half)] {
+            written += match target.write_all_at(part, at) {
+                Ok(()) => part.len() as u64,
+                Err(e) if is_media_error(&e) => self.bisect(target, part, at, bad)?,
+                Err(e) => return Err(Error::io_at(self.path, "write", at, e)),
+            };
+        }
+        Ok(written)
+    }
+
+    /// Reads back every region, except the sectors in `bad`, and compares
+    /// it to `data`. Up to the queue depth of chunks are read at once, into
+    /// page-aligned buffers, so that `target` may be a direct reader.
+    /// `on_progress` receives the bytes of the pass covered so far. Returns
+    /// the number of bytes verified.
+    pub(crate) fn verify_pass(
+        &self,
+        target: &dyn BlockTarget,
+        data: &mut PassData,
+        bad: &BadSectors,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        let mut queue = self.queue(target);
+        let mut expected = vec![0u8; self.chunk];
+        let mut covered = 0;
+        let mut verified = 0;
+
+        let mut chunks = self.chunks(0).peekable();
+        while let Some(&(offset, _)) = chunks.peek() {
+            if let Some(interrupted) = self.interrupted(offset) {
+                return Err(interrupted);
+            }
+            let batch: Vec<(u64, usize)> = chunks.by_ref().take(self.depth).collect();
+            self.read_batch(target, &batch, &mut queue, bad)?;
+
+            for (&(offset, len), actual) in batch.iter().zip(queue.buffers()) {
+                data.fill(offset, &mut expected[..len]);
+                for part in bad.good_parts(offset..offset + len as u64) {
+                    let at = (part.start - offset) as usize..(part.end - offset) as usize;
+                    // In a dry run nothing was written, so the old contents cannot match.
+                    let mismatch = if self.dry_run {
+                        None
+                    } else {
+                        actual[at.clone()]
+                            .iter()
+                            .zip(&expected[at.clone()])
+                            .position(|(a, b)| a != b)
+                    };
+                    if let Some(pos) = mismatch {
+                        return Err(Error::VerifyMismatch {
+                            path: self.path.to_path_buf(),
+                            offset: part.start + pos as u64,
+                        });
+                    }
+                    verified += at.len() as u64;
+                }
+                covered += len as u64;
+                on_progress(covered);
+            }
+        }
+
+        Ok(verified)
+    }
+
+    /// Notes that the ring was used, if it completed any chunk of a batch.
+    fn record_ring(&self, done: &[bool]) {
+        if done.contains(&true) {
+            self.ring_used.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Reads the good parts of the chunks of `batch` into the matching
+    /// buffers of `queue`. Chunks the ring did not read in full are read
+    /// again with blocking calls.
+    fn read_batch(
+        &self,
+        target: &dyn BlockTarget,
+        batch: &[(u64, usize)],
+        queue: &mut Queue,
+        bad: &BadSectors,
+    ) -> Result<()> {
+        let done = queue
+            .read(batch.len(), &Engine::clean(batch, bad))
+            .inspect(|done| self.record_ring(done))
+            .unwrap_or_else(|| vec![false; batch.len()]);
+        for ((&(offset, len), buffer), done) in batch.iter().zip(queue.buffers_mut()).zip(done) {
+            if done {
+                continue;
+            }
+            for part in bad.good_parts(offset..offset + len as u64) {
+                let at = (part.start - offset) as usize..(part.end - offset) as usize;
+                target
+                    .read_exact_at(&mut buffer[at], part.start)
+                    .map_err(|e| Error::io_at(self.path, "read", part.start, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A chunk for a writer thread: its offset, length, and the sectors known
+/// to be bad when it was handed out.
+type Chunk = (u64, usize, BadSectors);
+
+/// The writer threads of a pass: chunks go in through `work`, and come
+/// back through `done` with their result and the bad sectors found.
+struct Pool {
+    work: SyncSender<Chunk>,
+    done: Receiver<(u64, usize, Result<u64>, BadSectors)>,
+}
+
+/// The buffers for the chunks in flight, and the ring they are registered
+/// with when using io_uring.
+enum Queue {
+    Sync(Vec<AlignedBuf>),
+    #[cfg(feature = "io-uring")]
+    Ring(Box<Ring>),
+}
+
+impl Queue {
+    fn buffers(&self) -> &[AlignedBuf] {
+        match self {
+            Queue::Sync(buffers) => buffers,
+            #[cfg(feature = "io-uring")]
+            Queue::Ring(ring) => ring.buffers(),
+        }
+    }
+
+    fn buffers_mut(&mut self) -> &mut [AlignedBuf] {
+        match self {
+            Queue::Sync(buffers) => buffers,
+            #[cfg(feature = "io-uring")]
+            Queue::Ring(ring) => ring.buffers_mut(),
+        }
+    }
+
+    /// Writes `(buffer, offset, len)` for each of `ops` through the ring,
+    /// all at once. Returns which of the `count` buffers were written in
+    /// full, or `None` without a ring.
+    #[cfg_attr(not(feature = "io-uring"), allow(unused_variables))]
+    fn write(&mut self, count: usize, ops: &[(usize, u64, usize)]) -> Option<Vec<bool>> {
+        match self {
+            Queue::Sync(_) => None,
+            #[cfg(feature = "io-uring")]
+            Queue::Ring(ring) => Some(Queue::completed(count, ops, ring.write(ops))),
+        }
+    }
+
+    /// Like [`write`](Queue::write), reading into the buffers.
+    #[cfg_attr(not(feature = "io-uring"), allow(unused_variables))]
+    fn read(&mut self, count: usize, ops: &[(usize, u64, usize)]) -> Option<Vec<bool>> {
+        match self {
+            Queue::Sync(_) => None,
+            #[cfg(feature = "io-uring")]
+            Queue::Ring(ring) => Some(Queue::completed(count, ops, ring.read(ops))),
+        }
+    }
+
+    #[cfg(feature = "io-uring")]
+    fn completed(
+        count: usize,
+        ops: &[(usize, u64, usize)],
+        results: Vec<std::io::Result<usize>>,
+    ) -> Vec<bool> {
+        let mut done = vec![false; count];
+        for (&(i, _, len), result) in ops.iter().zip(results) {
+            done[i] = matches!(result, Ok(n) if n == len);
+        }
+        done
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned by the wipers library.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O operation on `path` failed, at `offset` if it was a read or
+    /// write.
+    Io {
+        path: PathBuf,
+        op: &'static str,
+        offset: Option<u64>,
+        source: io::Error,
+    },
+    /// Verification read back data that differs from what was written.
+    VerifyMismatch { path: PathBuf, offset: u64 },
+    /// The job was configured with invalid parameters.
+    InvalidJob(String),
+    /// A wipe method definition cannot be executed.
+    InvalidMethod(String),
+    /// A policy file could not be parsed.
+    InvalidPolicy(String),
+    /// A target selector is malformed, matches nothing, or is ambiguous.
+    InvalidSelector(String),
+    /// The target is protected from wiping.
+    Protected { path: PathBuf, reason: String },
+    /// The drive behind the target is no longer the one the job started on.
+    IdentityChanged { path: PathBuf, reason: String },
+    /// Another process or kernel user holds the target.
+    Busy { path: PathBuf, users: Vec<String> },
+    /// The job was asked to stop and did so at a chunk boundary, after
+    /// flushing the target.
+    Interrupted {
+        path: PathBuf,
+        pass: u32,
+        offset: u64,
+    },
+}
+
+/// Convenience alias used throughout the crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>, op: &'static str, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            op,
+            offset: None,
+            source,
+        }
+    }
+
+    /// An I/O error reading or writing `path` at byte `offset`.
+    pub(crate) fn io_at(
+        path: impl Into<PathBuf>,
+        op: &'static str,
+        offset: u64,
+        source: io::Error,
+    ) -> Self {
+        Error::Io {
+            path: path.into(),
+            op,
+            offset: Some(offset),
+            source,
+        }
+    }
+
+    /// True if the error means the device itself is gone: the kernel reports
+    /// no such device, or its node has disappeared.
+    pub fn is_vanished(&self) -> bool {
+        match self {
+            Error::Io { path, source, .. } => {
+                matches!(
+                    source.raw_os_error(),
+                    Some(libc::ENODEV | libc::ENXIO | libc::ENOMEDIUM)
+                ) || !path.exists()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io {
+                path,
+                op,
+                offset: None,
+                source,
+            } => write!(f, "failed to {} {}: {}", op, path.display(), source),
+            Error::Io {
+                path,
+                op,
+                offset: Some(offset),
+                source,
+            } => write!(
+                f,
+                "failed to {} {} at byte {}: {}",
+                op,
+                path.display(),
+                offset,
+                source
+            ),
+            Error::VerifyMismatch { path, offset } => write!(
+                f,
+                "verification failed on {}: unexpected data at byte {}",
+                path.display(),
+                offset
+            ),
+            Error::InvalidJob(msg) => write!(f, "invalid wipe job: {}", msg),
+            Error::InvalidMethod(msg) => write!(f, "invalid wipe method: {}", msg),
+            Error::InvalidPolicy(msg) => write!(f, "invalid policy: {}", msg),
+            Error::InvalidSelector(msg) => write!(f, "invalid target selector: {}", msg),
+            Error::Protected { path, reason } => {
+                write!(f, "refusing to wipe {}: {}", path.display(), reason)
+            }
+            Error::IdentityChanged { path, reason } => write!(
+                f,
+                "{} is no longer the drive the wipe started on: {}",
+                path.display(),
+                reason
+            ),
+            Error::Busy { path, users } if users.is_empty() => {
+                write!(f, "{} is opened exclusively elsewhere", path.display())
+            }
+            Error::Busy { path, users } => {
+                write!(f, "{} is busy: {}", path.display(), users.join("; "))
+            }
+            Error::Interrupted { path, pass, offset } => write!(
+                f,
+                "wipe of {} interrupted in pass {} at byte {}",
+                path.display(),
+                pass,
+                offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Pins a job to the physical drive it started on, so that a renumbered
+//! `/dev/sdX` after a hot-plug or bus reset is never written to by mistake.
+
+use crate::discovery::Drive;
+use crate::error::{Error, Result};
+use crate::sysfs::{BlockDev, DevId, SysRoot};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// What identifies a drive independently of the node that names it. A
+/// partition is identified by its disk's serial and WWN, and its own start
+/// and size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    /// Kernel name when the job started, such as `sdb` or `sdb1`.
+    pub name: String,
+    pub dev: DevId,
+    pub serial: Option<String>,
+    pub wwn: Option<String>,
+    pub size: u64,
+    /// Byte offset of a partition on its disk; `None` for a whole disk.
+    #[serde(default)]
+    pub start: Option<u64>,
+    /// Path the job opens: a `/dev/disk/by-id` link if there is one,
+    /// otherwise the kernel node.
+    pub stable_path: PathBuf,
+}
+
+impl Identity {
+    /// Pins the identity `drive` reports now.
+    pub fn of(drive: &Drive) -> Identity {
+        // WWNs are the most specific; the other by-id links embed the serial.
+        let stable_path = drive
+            .by_id
+            .iter()
+            .find(|link| link_name(link).starts_with("wwn-"))
+            .or_else(|| drive.by_id.first())
+            .cloned()
+            .unwrap_or_else(|| drive.path.clone());
+        Identity {
+            name: drive.name.clone(),
+            dev: drive.dev,
+            serial: drive.serial.clone(),
+            wwn: drive.wwn.clone(),
+            size: drive.size,
+            start: drive.partition_of.as_ref().map(|p| p.start),
+            stable_path,
+        }
+    }
+
+    /// Describes the first difference between the pinned identity and
+    /// `drive`, if any.
+    fn difference(&self, drive: &Drive) -> Option<String> {
+        let field = |what: &str, pinned: &Option<String>, now: &Option<String>| {
+            (pinned != now).then(|| {
+                format!(
+                    "{} changed from {} to {}",
+                    what,
+                    pinned.as_deref().unwrap_or("none"),
+                    now.as_deref().unwrap_or("none")
+                )
+            })
+        };
+        field("serial", &self.serial, &drive.serial)
+            .or_else(|| field("WWN", &self.wwn, &drive.wwn))
+            .or_else(|| {
+                (self.size != drive.size)
+                    .then(|| format!("size changed from {} to {}", self.size, drive.size))
+            })
+            .or_else(|| {
+                let start = drive.partition_of.as_ref().map(|p| p.start);
+                (self.start != start).then(|| {
+                    let at = |start: Option<u64>| match start {
+                        Some(start) => format!("offset {}", start),
+                        None => "the whole disk".to_string(),
+                    };
+                    format!("partition moved from {} to {}", at(self.start), at(start))
+                })
+            })
+    }
+
+    fn changed(&self, reason: String) -> Error {
+        Error::IdentityChanged {
+            path: self.stable_path.clone(),
+            reason,
+        }
+    }
+
+    /// Checks that sysfs still reports the same serial, WWN and size for the
+    /// pinned device number, and that the stable path still leads to it.
+    pub fn check(&self, sys: &SysRoot) -> Result<()> {
+        let name = sys
+            .name_of(self.dev)
+            .map_err(|_| self.changed(format!("device {} is gone", self.dev)))?;
+        let drive = sys
+            .drive(&name)
+            .map_err(|_| self.changed(format!("{} is no longer in sysfs", name)))?;
+        if let Some(reason) = self.difference(&drive) {
+            return Err(self.changed(reason));
+        }
+        match self.follow_stable_path(sys) {
+            Some(now) if now.dev == self.dev => Ok(()),
+            Some(now) => Err(self.changed(format!(
+                "{} now leads to {}",
+                self.stable_path.display(),
+                now.name
+            ))),
+            None => Err(self.changed(format!("{} no longer exists", self.stable_path.display()))),
+        }
+    }
+
+    /// The same drive as it is attached now. After a power cycle or a
+    /// re-plug it may come back under a new name and device number, so it
+    /// is found through the stable path and must still report the pinned
+    /// serial, WWN and size.
+    pub fn relocate(&self, sys: &SysRoot) -> Result<Identity> {
+        let now = self.follow_stable_path(sys).ok_or_else(|| {
+            self.changed(format!("{} no longer exists", self.stable_path.display()))
+        })?;
+        let drive = sys
+            .drive(&now.name)
+            .map_err(|_| self.changed(format!("{} is no longer in sysfs", now.name)))?;
+        if let Some(reason) = self.difference(&drive) {
+            return Err(self.changed(reason));
+        }
+        Ok(Identity {
+            stable_path: self.stable_path.clone(),
+            ..Identity::of(&drive)
+        })
+    }
+
+    fn follow_stable_path(&self, sys: &SysRoot) -> Option<BlockDev> {
+        let rel = self
+            .stable_path
+            .strip_prefix("/dev")
+            .unwrap_or(&self.stable_path);
+        sys.by_path(&sys.dev_path(&rel.to_string_lossy()))
+    }
+
+    /// `check`, plus that `file`, opened for the job, is still the pinned
+    /// device number.
+    pub fn check_open(&self, sys: &SysRoot, file: &File) -> Result<()> {
+        let metadata = file
+            .metadata()
+            .map_err(|e| Error::io(&self.stable_path, "stat", e))?;
+        let dev = DevId::from_raw(metadata.rdev());
+        if dev != self.dev {
+            return Err(self.changed(format!(
+                "device number changed from {} to {}",
+                self.dev, dev
+            )));
+        }
+        self.check(sys)
+    }
+}
+
+fn link_name(path: &Path) -> &str {
+    path.file_name().and_then(|n| n.to_str()).unwrap_or("")
+}
@@ -0,0 +1,896 @@
+use crate::bad_sectors::{BadSectorPolicy, BadSectors};
+use crate::checkpoint::{self, Checkpoint, Phase, Resumed, RngPosition};
+use crate::device::Geometry;
+use crate::engine::{Engine, CHUNK_SIZE};
+use crate::error::{Error, Result};
+use crate::identity::Identity;
+use crate::lock::{self, TargetLock};
+use crate::method::{Fill, Pass, Pattern, WipeMethod};
+use crate::report;
+use crate::rng::RunSecret;
+use crate::sample::{Sample, Sampling};
+use crate::sysfs::SysRoot;
+use crate::target::{self, BlockTarget};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default bytes written between checkpoints within a pass.
+const CHECKPOINT_INTERVAL: u64 = 1024 * 1024 * 1024;
+
+/// Writes kept in flight at once on each target unless configured otherwise.
+pub const DEFAULT_QUEUE_DEPTH: usize = 4;
+
+/// When to read the device back after writing, and how much of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerifyPolicy {
+    /// Only verify passes the method marks for verification.
+    Never,
+    /// Additionally verify the final pass.
+    AfterLastPass,
+    /// Verify every pass.
+    EveryPass,
+    /// Verify the final pass, and those the method marks, by reading back
+    /// only a sample of the target.
+    Sample(Sampling),
+}
+
+impl VerifyPolicy {
+    /// Whether pass `pass` of `passes` is read back.
+    fn verifies(&self, step: &Pass, pass: u32, passes: u32) -> bool {
+        match self {
+            VerifyPolicy::Never => step.verify,
+            VerifyPolicy::AfterLastPass | VerifyPolicy::Sample(_) => step.verify || pass == passes,
+            VerifyPolicy::EveryPass => true,
+        }
+    }
+}
+
+/// How regular files are overwritten. Block devices are always overwritten
+/// in full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SparseMode {
+    /// Overwrite the whole file, allocating any holes.
+    Allocate,
+    /// Overwrite only the extents that hold data, found with `SEEK_DATA`
+    /// and `SEEK_HOLE`, then punch them out so the file ends up empty and
+    /// sparse.
+    Extents,
+}
+
+/// How the passes submit their reads and writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoBackend {
+    /// Blocking calls, with one thread per chunk in flight.
+    #[default]
+    Sync,
+    /// io_uring, with the chunk buffers registered with the kernel and up
+    /// to the queue depth of chunks in flight. Needs the `io-uring` cargo
+    /// feature. Jobs fall back to `Sync` without it, where the kernel does
+    /// not allow io_uring, and for targets that are not backed by a file
+    /// descriptor.
+    IoUring,
+}
+
+impl IoBackend {
+    /// Whether this backend can be used in this build and on this kernel.
+    pub fn is_available(self) -> bool {
+        match self {
+            IoBackend::Sync => true,
+            #[cfg(feature = "io-uring")]
+            IoBackend::IoUring => crate::uring::available(),
+            #[cfg(not(feature = "io-uring"))]
+            IoBackend::IoUring => false,
+        }
+    }
+}
+
+/// Progress notifications emitted while a job runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    PassStarted {
+        pass: u32,
+        passes: u32,
+        pattern: Pattern,
+    },
+    Progress {
+        pass: u32,
+        written: u64,
+        total: u64,
+    },
+    PassFinished {
+        pass: u32,
+    },
+    VerifyStarted {
+        pass: u32,
+    },
+    VerifyProgress {
+        pass: u32,
+        read: u64,
+        total: u64,
+    },
+}
+
+/// Result of the verification steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// Verification was not requested.
+    Skipped,
+    /// The data read back matched every verified pass.
+    Passed,
+    /// A dry run read the verified passes back without comparing them.
+    DryRun,
+}
+
+/// Summary of a successfully completed job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WipeOutcome {
+    pub target: PathBuf,
+    pub method: WipeMethod,
+    pub geometry: Geometry,
+    pub passes: u32,
+    /// Bytes written by each pass: the target size, or the allocated bytes
+    /// of a file wiped with `SparseMode::Extents`.
+    pub bytes_per_pass: u64,
+    /// Total bytes written across all passes.
+    pub bytes_written: u64,
+    /// Total bytes read back and compared.
+    pub bytes_verified: u64,
+    pub verification: Verification,
+    /// What was read back, when verifying by sampling.
+    pub sample: Option<Sample>,
+    /// The drive the job was pinned to; `None` for regular files.
+    pub identity: Option<Identity>,
+    /// Nothing was written: the job ran with a no-op write sink.
+    pub dry_run: bool,
+    /// Where the job continued from, if it was resumed from a journal.
+    pub resumed: Option<Resumed>,
+    /// Logical sectors that could not be written and were skipped, as
+    /// end-exclusive LBA ranges.
+    pub bad_sectors: Vec<Range<u64>>,
+    /// Passes were written with `O_DIRECT`, bypassing the page cache.
+    pub direct_io: bool,
+    /// How the data was moved: `IoBackend::IoUring` only if the ring was
+    /// actually used, not merely asked for.
+    pub io_backend: IoBackend,
+}
+
+/// How much a job will move and roughly how long it will take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub bytes_written: u64,
+    pub bytes_verified: u64,
+    pub duration: Duration,
+}
+
+/// How one device's job ended. Failures carry the error that stopped it.
+#[derive(Debug)]
+pub enum JobStatus {
+    /// Every pass was written and every requested verification passed.
+    Wiped(WipeOutcome),
+    /// Every pass was written except to sectors the device could not
+    /// write, which were skipped and are listed in the outcome.
+    CompletedWithBadSectors(WipeOutcome),
+    /// Data read back did not match what was written.
+    VerifyFailed(Error),
+    /// Reading or writing the device failed.
+    IoError(Error),
+    /// The device disappeared while the job was running.
+    Vanished(Error),
+    /// A different drive appeared behind the target, so the job stopped.
+    IdentityChanged(Error),
+    /// The job was asked to stop, and stopped cleanly at a chunk boundary.
+    Interrupted(Error),
+    /// The job was refused before anything was written.
+    Refused(Error),
+}
+
+impl JobStatus {
+    pub fn is_success(&self) -> bool {
+        matches!(self, JobStatus::Wiped(_))
+    }
+}
+
+impl From<Result<WipeOutcome>> for JobStatus {
+    fn from(result: Result<WipeOutcome>) -> Self {
+        match result {
+            Ok(outcome) if !outcome.bad_sectors.is_empty() => {
+                JobStatus::CompletedWithBadSectors(outcome)
+            }
+            Ok(outcome) => JobStatus::Wiped(outcome),
+            Err(e @ Error::VerifyMismatch { .. }) => JobStatus::VerifyFailed(e),
+            Err(e @ Error::IdentityChanged { .. }) => JobStatus::IdentityChanged(e),
+            Err(e @ Error::Interrupted { .. }) => JobStatus::Interrupted(e),
+            Err(e @ Error::Io { .. }) if e.is_vanished() => JobStatus::Vanished(e),
+            Err(e @ Error::Io { .. }) => JobStatus::IoError(e),
+            Err(e) => JobStatus::Refused(e),
+        }
+    }
+}
+
+/// Fills in the pass number of an interruption reported by the engine.
+fn in_pass(error: Error, pass: u32) -> Error {
+    match error {
+        Error::Interrupted { path, offset, .. } => Error::Interrupted { path, pass, offset },
+        error => error,
+    }
+}
+
+/// Writes back and drops what the kernel caches of `target`, so that
+/// verification reads what the storage returns rather than cached pages.
+fn invalidate_cache(target: &dyn BlockTarget, path: &Path) -> Result<()> {
+    target
+        .invalidate_cache()
+        .map_err(|e| Error::io(path, "flush the cache of", e))
+}
+
+/// A handle reading `target` around the page cache, if it has one.
+fn direct_reader(target: &dyn BlockTarget, path: &Path) -> Result<Option<Box<dyn BlockTarget>>> {
+    target
+        .direct_reader()
+        .map_err(|e| Error::io(path, "open for direct reads", e))
+}
+
+/// A wipe of a single target, configured with builder methods.
+///
+/// ```no_run
+/// use wipers::{VerifyPolicy, WipeJob, WipeMethod};
+///
+/// let outcome = WipeJob::new("/dev/sdx")
+///     .method(WipeMethod::standard("dod")?)
+///     .rounds(2)
+///     .verify(VerifyPolicy::AfterLastPass)
+///     .run(|_event| {})?;
+/// # Ok::<(), wipers::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WipeJob {
+    target: PathBuf,
+    method: WipeMethod,
+    rounds: u32,
+    verify: VerifyPolicy,
+    secret: RunSecret,
+    identity: Option<Identity>,
+    dry_run: bool,
+    sparse: SparseMode,
+    on_bad_sector: BadSectorPolicy,
+    block_size: usize,
+    queue_depth: usize,
+    io_backend: IoBackend,
+    direct_io: bool,
+    journal: Option<PathBuf>,
+    checkpoint_interval: u64,
+    resume: Option<Checkpoint>,
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl WipeJob {
+    /// Creates a single-pass zero wipe of `target` without verification.
+    pub fn new(target: impl Into<PathBuf>) -> Self {
+        WipeJob {
+            target: target.into(),
+            method: WipeMethod::zero(),
+            rounds: 1,
+            verify: VerifyPolicy::Never,
+            secret: RunSecret::generate(),
+            identity: None,
+            dry_run: false,
+            sparse: SparseMode::Allocate,
+            on_bad_sector: BadSectorPolicy::Abort,
+            block_size: CHUNK_SIZE,
+            queue_depth: DEFAULT_QUEUE_DEPTH,
+            io_backend: IoBackend::Sync,
+            direct_io: true,
+            journal: None,
+            checkpoint_interval: CHECKPOINT_INTERVAL,
+            resume: None,
+            stop: None,
+        }
+    }
+
+    /// Continues the wipe recorded in `checkpoint` where it left off. The
+    /// drive must still report the identity pinned when the wipe started,
+    /// though it may have been renumbered since. It writes with the block
+    /// size, queue depth, backend and direct I/O setting it was started
+    /// with, unless they are changed again. The journal does not keep
+    /// the run secret; if random passes are left, pass the one the wipe
+    /// started with to [`secret`](WipeJob::secret).
+    pub fn resume(checkpoint: Checkpoint) -> Result<WipeJob> {
+        Ok(WipeJob {
+            target: checkpoint.target.clone(),
+            method: checkpoint.method.clone(),
+            rounds: checkpoint.rounds,
+            verify: checkpoint.verify,
+            secret: RunSecret::generate(),
+            identity: checkpoint.identity.clone(),
+            dry_run: false,
+            sparse: checkpoint.sparse,
+            on_bad_sector: checkpoint.on_bad_sector,
+            block_size: checkpoint.block_size,
+            queue_depth: checkpoint.queue_depth,
+            io_backend: checkpoint.io_backend,
+            direct_io: checkpoint.direct_io,
+            journal: None,
+            checkpoint_interval: CHECKPOINT_INTERVAL,
+            resume: Some(checkpoint),
+            stop: None,
+        })
+    }
+
+    pub fn method(mut self, method: WipeMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Number of times the method's pass sequence is run back to back.
+    pub fn rounds(mut self, rounds: u32) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    pub fn verify(mut self, verify: VerifyPolicy) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Keys the random passes. A fresh secret is generated by default.
+    pub fn secret(mut self, secret: RunSecret) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// Pins the job to a drive identified earlier, for example when it was
+    /// checked against a policy. Otherwise the job pins whatever drive the
+    /// target names when it starts.
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Runs every step against the real target, including opening, locking
+    /// and identity checks, but sends all writes to a no-op sink.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// How a regular file target is overwritten; `Allocate` by default.
+    pub fn sparse(mut self, sparse: SparseMode) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// What to do when the target cannot write a sector; `Abort` by
+    /// default. With `Skip`, the job completes with the unwritable sectors
+    /// listed in its outcome, and verification passes over them.
+    pub fn on_bad_sector(mut self, policy: BadSectorPolicy) -> Self {
+        self.on_bad_sector = policy;
+        self
+    }
+
+    /// Bytes per write and per read, rounded up to a whole number of
+    /// sectors; 1 MiB by default.
+    pub fn block_size(mut self, bytes: usize) -> Self {
+        self.block_size = bytes;
+        self
+    }
+
+    /// Writes kept in flight at once, each from its own buffer;
+    /// [`DEFAULT_QUEUE_DEPTH`] by default. With the `Sync` backend, each
+    /// buffer has a writer thread for the whole pass, which takes the next
+    /// chunk as soon as its write completes.
+    pub fn queue_depth(mut self, depth: usize) -> Self {
+        self.queue_depth = depth.max(1);
+        self
+    }
+
+    /// How reads and writes are submitted; `Sync` by default.
+    pub fn io_backend(mut self, backend: IoBackend) -> Self {
+        self.io_backend = backend;
+        self
+    }
+
+    /// Writes with `O_DIRECT` from page-aligned buffers, bypassing the page
+    /// cache, where the target supports it; on by default. Targets that
+    /// reject direct I/O, and writes that are not sector-aligned, go
+    /// through the page cache regardless.
+    pub fn direct_io(mut self, direct_io: bool) -> Self {
+        self.direct_io = direct_io;
+        self
+    }
+
+    /// Keeps a checkpoint journal at `path` so that an interrupted job can
+    /// be resumed. It is removed once the job completes. Dry runs keep none.
+    pub fn journal(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal = Some(path.into());
+        self
+    }
+
+    /// Bytes written between checkpoints within a pass; 1 GiB by default.
+    /// Each checkpoint flushes the target first.
+    pub fn checkpoint_interval(mut self, bytes: u64) -> Self {
+        self.checkpoint_interval = bytes.max(1);
+        self
+    }
+
+    /// Stops the job at the next chunk boundary once `stop` is set: the
+    /// target is flushed, the journal brought up to date, and the job fails
+    /// with [`Error::Interrupted`].
+    pub fn stop_when(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Every pass of every round, paired with its resolved fill.
+    fn schedule(&self) -> Result<Vec<(&Pass, Fill)>> {
+        if self.rounds == 0 {
+            return Err(Error::InvalidJob("at least one round is required".into()));
+        }
+        self.method.validate()?;
+        let fills = self.method.resolve()?;
+        Ok((0..self.rounds)
+            .flat_map(|_| self.method.passes.iter().zip(fills.iter().cloned()))
+            .collect())
+    }
+
+    /// Bytes the job will write and verify on a target of `size` bytes, and
+    /// the time that takes at `throughput` bytes per second.
+    pub fn estimate(&self, size: u64, throughput: u64) -> Result<Estimate> {
+        let schedule = self.schedule()?;
+        let passes = schedule.len() as u32;
+        let verified = (1..)
+            .zip(&schedule)
+            .filter(|(pass, (step, _))| self.verify.verifies(step, *pass, passes))
+            .count() as u64;
+        let per_verify = match self.verify {
+            VerifyPolicy::Sample(sampling) => {
+                let whole: Vec<_> = std::iter::once(0..size).collect();
+                Sample::new(sampling, &whole).bytes
+            }
+            _ => size,
+        };
+        let bytes_written = size * u64::from(passes);
+        let bytes_verified = per_verify * verified;
+        Ok(Estimate {
+            bytes_written,
+            bytes_verified,
+            duration: Duration::from_secs((bytes_written + bytes_verified) / throughput.max(1)),
+        })
+    }
+
+    /// The identity to hold the target to, and the path to open it by.
+    fn pin(&self, sys: &SysRoot) -> Result<(Option<Identity>, PathBuf)> {
+        let identity = match &self.identity {
+            Some(identity) if self.resume.is_some() => Some(identity.relocate(sys)?),
+            Some(identity) => Some(identity.clone()),
+            None => sys
+                .drive_for(&self.target)?
+                .map(|drive| Identity::of(&drive)),
+        };
+        let path = identity
+            .as_ref()
+            .map_or_else(|| self.target.clone(), |id| id.stable_path.clone());
+        Ok((identity, path))
+    }
+
+    /// Runs the job, reporting progress through `on_event`.
+    pub fn run(&self, mut on_event: impl FnMut(&Event)) -> Result<WipeOutcome> {
+        self.schedule()?;
+        let sys = SysRoot::default();
+        let (identity, path) = self.pin(&sys)?;
+        let _lock = TargetLock::acquire(&path)?;
+        let file = lock::open_exclusive(&path, self.dry_run)?;
+        let target = target::open_target(&file, &path)?;
+        let check_identity = || match &identity {
+            Some(identity) => identity.check_open(&sys, &file),
+            None => Ok(()),
+        };
+        let mut outcome = self.execute(
+            &*target,
+            &path,
+            identity.as_ref(),
+            check_identity,
+            &mut on_event,
+        )?;
+        outcome.identity = identity;
+        Ok(outcome)
+    }
+
+    /// Runs the job against an already open target, such as a
+    /// [`MemoryTarget`](crate::MemoryTarget), without locking it or checking
+    /// its identity. The job's target path names it in errors, and a
+    /// failure counts as the target vanishing if that path no longer exists.
+    pub fn run_on(
+        &self,
+        target: &dyn BlockTarget,
+        mut on_event: impl FnMut(&Event),
+    ) -> Result<WipeOutcome> {
+        self.execute(
+            target,
+            &self.target,
+            self.identity.as_ref(),
+            || Ok(()),
+            &mut on_event,
+        )
+    }
+
+    /// Checks that a checkpoint being resumed describes this schedule on
+    /// this target.
+    fn check_resume(
+        &self,
+        checkpoint: &Checkpoint,
+        schedule: &[(&Pass, Fill)],
+        geometry: Geometry,
+    ) -> Result<()> {
+        let mismatch = |what: &str| {
+            Err(Error::InvalidJob(format!(
+                "the journal does not match the target: {}",
+                what
+            )))
+        };
+        if checkpoint.passes as usize != schedule.len() {
+            return mismatch("the number of passes differs");
+        }
+        if checkpoint.needs_secret() && !checkpoint.matches_secret(&self.secret) {
+            return Err(Error::InvalidJob(
+                "the random passes left need the run secret the wipe started with".into(),
+            ));
+        }
+        if checkpoint.regions.iter().any(|r| r.end > geometry.size) {
+            return mismatch("the target is smaller than recorded");
+        }
+        if checkpoint.phase == Phase::Write
+            && checkpoint.rng != self.rng_position(schedule, checkpoint.pass, checkpoint.offset)
+        {
+            return mismatch("the random stream position differs");
+        }
+        Ok(())
+    }
+
+    /// Where the random stream of `pass` stands at `offset`, if it is random.
+    fn rng_position(
+        &self,
+        schedule: &[(&Pass, Fill)],
+        pass: u32,
+        offset: u64,
+    ) -> Option<RngPosition> {
+        let (_, fill) = schedule.get(pass.checked_sub(1)? as usize)?;
+        fill.data(&self.secret, pass).rng_position(offset)
+    }
+
+    /// The engine that reads passes back: `engine` itself, or one limited
+    /// to the sample when verifying by sampling.
+    fn checker<'a>(&self, engine: &Engine<'a>) -> (Engine<'a>, Option<Sample>) {
+        match self.verify {
+            VerifyPolicy::Sample(sampling) => {
+                let sample = Sample::new(sampling, engine.regions());
+                let checker = engine.clone().restrict_to(sample.regions.clone());
+                (checker, Some(sample))
+            }
+            _ => (engine.clone(), None),
+        }
+    }
+
+    fn execute(
+        &self,
+        target: &dyn BlockTarget,
+        path: &Path,
+        identity: Option<&Identity>,
+        check_identity: impl Fn() -> Result<()>,
+        on_event: &mut dyn FnMut(&Event),
+    ) -> Result<WipeOutcome> {
+        let schedule = self.schedule()?;
+        let passes = schedule.len() as u32;
+
+        let geometry = target.geometry();
+        let mut engine = Engine::new(path, geometry)
+            .dry_run(self.dry_run)
+            .stop_when(self.stop.clone())
+            .on_bad_sector(self.on_bad_sector)
+            .chunk_size(self.block_size)
+            .queue_depth(self.queue_depth)
+            .io_backend(self.io_backend);
+        let mut extents = false;
+        if self.sparse == SparseMode::Extents {
+            let regions = target
+                .data_extents()
+                .map_err(|e| Error::io(path, "find the data extents of", e))?;
+            if let Some(regions) = regions {
+                engine = engine.restrict_to(regions);
+                extents = true;
+            }
+        }
+
+        let journal = self.journal.as_deref().filter(|_| !self.dry_run);
+        let record = |checkpoint: &Checkpoint| match journal {
+            Some(journal) => checkpoint.save(journal),
+            None => Ok(()),
+        };
+        let mut checkpoint = match &self.resume {
+            Some(checkpoint) => {
+                self.check_resume(checkpoint, &schedule, geometry)?;
+                engine = engine.restrict_to(checkpoint.regions.clone());
+                Checkpoint {
+                    identity: identity.cloned(),
+                    block_size: self.block_size,
+                    queue_depth: self.queue_depth,
+                    io_backend: self.io_backend,
+                    direct_io: self.direct_io,
+                    interruptions: checkpoint.interruptions + 1,
+                    ..checkpoint.clone()
+                }
+            }
+            None => Checkpoint {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                target: self.target.clone(),
+                identity: identity.cloned(),
+                method: self.method.clone(),
+                rounds: self.rounds,
+                verify: self.verify,
+                sparse: self.sparse,
+                on_bad_sector: self.on_bad_sector,
+                block_size: self.block_size,
+                queue_depth: self.queue_depth,
+                io_backend: self.io_backend,
+                direct_io: self.direct_io,
+                secret_id: schedule
+                    .iter()
+                    .any(|(_, fill)| fill.pattern == Pattern::Random)
+                    .then(|| self.secret.fingerprint()),
+                regions: engine.regions().to_vec(),
+                pass: 1,
+                passes,
+                phase: Phase::Write,
+                offset: 0,
+                rng: self.rng_position(&schedule, 1, 0),
+                bytes_written: 0,
+                bytes_verified: 0,
+                verified: false,
+                started: report::now(),
+                interruptions: 0,
+                bad_sectors: Vec::new(),
+            },
+        };
+        let mut bad = BadSectors::new(geometry.logical_sector_size, &checkpoint.bad_sectors);
+        let resumed = self.resume.as_ref().map(|_| Resumed {
+            pass: checkpoint.pass,
+            phase: checkpoint.phase,
+            offset: checkpoint.offset,
+            interruptions: checkpoint.interruptions,
+        });
+        record(&checkpoint)?;
+        engine = engine.flush_every(journal.map(|_| self.checkpoint_interval));
+        let total = engine.extent();
+        let (checker, sample) = self.checker(&engine);
+        let checked = checker.extent();
+        let direct = direct_reader(target, path)?;
+        let reader = direct.as_deref().unwrap_or(target);
+        let direct_writer = if self.direct_io && !self.dry_run {
+            target
+                .direct_writer()
+                .map_err(|e| Error::io(path, "open for direct writes", e))?
+        } else {
+            None
+        };
+        let writer = direct_writer.as_deref().unwrap_or(target);
+
+        let start = (checkpoint.pass, checkpoint.phase, checkpoint.offset);
+        let mut bytes_written = checkpoint.bytes_written;
+        let mut bytes_verified = checkpoint.bytes_verified;
+        let mut verification = if checkpoint.verified {
+            Verification::Passed
+        } else {
+            Verification::Skipped
+        };
+
+        for (pass, (step, fill)) in (1..).zip(&schedule) {
+            if pass < start.0 {
+                continue;
+            }
+            let verify = self.verify.verifies(step, pass, passes);
+
+            if pass > start.0 || start.1 == Phase::Write {
+                let from = if pass == start.0 { start.2 } else { 0 };
+                check_identity()?;
+                on_event(&Event::PassStarted {
+                    pass,
+                    passes,
+                    pattern: step.pattern.clone(),
+                });
+                let mut data = fill.data(&self.secret, pass);
+                let positions = fill.data(&self.secret, pass);
+                let before = bytes_written;
+                bytes_written += engine
+                    .write_pass(
+                        writer,
+                        &mut data,
+                        from,
+                        &mut bad,
+                        |written| {
+                            on_event(&Event::Progress {
+                                pass,
+                                written,
+                                total,
+                            })
+                        },
+                        |offset, written, bad| {
+                            checkpoint.offset = offset;
+                            checkpoint.bad_sectors = bad.lbas();
+                            checkpoint.rng = positions.rng_position(offset);
+                            checkpoint.bytes_written = before + written;
+                            record(&checkpoint)
+                        },
+                    )
+                    .map_err(|e| in_pass(e, pass))?;
+                on_event(&Event::PassFinished { pass });
+
+                checkpoint.bytes_written = bytes_written;
+                checkpoint.bad_sectors = bad.lbas();
+                if verify {
+                    checkpoint.phase = Phase::Verify;
+                    checkpoint.offset = 0;
+                    checkpoint.rng = None;
+                } else {
+                    checkpoint.pass = pass + 1;
+                    checkpoint.offset = 0;
+                    checkpoint.rng = self.rng_position(&schedule, pass + 1, 0);
+                }
+                record(&checkpoint)?;
+            }
+
+            if verify {
+                check_identity()?;
+                invalidate_cache(target, path)?;
+                on_event(&Event::VerifyStarted { pass });
+                let mut data = fill.data(&self.secret, pass);
+                bytes_verified += checker
+                    .verify_pass(reader, &mut data, &bad, |read| {
+                        on_event(&Event::VerifyProgress {
+                            pass,
+                            read,
+                            total: checked,
+                        })
+                    })
+                    .map_err(|e| in_pass(e, pass))?;
+                verification = if self.dry_run {
+                    Verification::DryRun
+                } else {
+                    Verification::Passed
+                };
+
+                checkpoint.pass = pass + 1;
+                checkpoint.phase = Phase::Write;
+                checkpoint.offset = 0;
+                checkpoint.rng = self.rng_position(&schedule, pass + 1, 0);
+                checkpoint.bytes_verified = bytes_verified;
+                checkpoint.verified = true;
+                record(&checkpoint)?;
+            }
+        }
+
+        if extents && !self.dry_run {
+            for region in engine.regions() {
+                target
+                    .discard(region.clone())
+                    .map_err(|e| Error::io(path, "punch holes in", e))?;
+            }
+            target.flush().map_err(|e| Error::io(path, "sync", e))?;
+        }
+        if let Some(journal) = journal {
+            checkpoint::remove(journal)?;
+        }
+
+        Ok(WipeOutcome {
+            target: self.target.clone(),
+            method: self.method.clone(),
+            geometry,
+            passes,
+            bytes_per_pass: total,
+            bytes_written,
+            bytes_verified,
+            verification,
+            sample: sample.filter(|_| verification != Verification::Skipped),
+            identity: None,
+            dry_run: self.dry_run,
+            resumed,
+            bad_sectors: bad.lbas(),
+            direct_io: direct_writer.is_some(),
+            io_backend: engine.used_backend(),
+        })
+    }
+
+    /// Checks, without writing, that the target still holds the data of the
+    /// job's final pass. Random passes need the secret of the original run.
+    pub fn verify_only(&self, mut on_event: impl FnMut(&Event)) -> Result<WipeOutcome> {
+        self.schedule()?;
+        let sys = SysRoot::default();
+        let (identity, path) = self.pin(&sys)?;
+        let _lock = TargetLock::acquire(&path)?;
+        let file = File::open(&path).map_err(|e| Error::io(&path, "open", e))?;
+        if let Some(identity) = &identity {
+            identity.check_open(&sys, &file)?;
+        }
+        let target = target::open_target(&file, &path)?;
+        let mut outcome = self.check(&*target, &path, &mut on_event)?;
+        outcome.identity = identity;
+        Ok(outcome)
+    }
+
+    /// Like [`verify_only`](WipeJob::verify_only), against an already open
+    /// target.
+    pub fn verify_on(
+        &self,
+        target: &dyn BlockTarget,
+        mut on_event: impl FnMut(&Event),
+    ) -> Result<WipeOutcome> {
+        self.check(target, &self.target, &mut on_event)
+    }
+
+    fn check(
+        &self,
+        target: &dyn BlockTarget,
+        path: &Path,
+        on_event: &mut dyn FnMut(&Event),
+    ) -> Result<WipeOutcome> {
+        let schedule = self.schedule()?;
+        let passes = schedule.len() as u32;
+        let (_, fill) = schedule.last().expect("schedule is never empty");
+
+        let geometry = target.geometry();
+        let engine = Engine::new(path, geometry)
+            .stop_when(self.stop.clone())
+            .chunk_size(self.block_size)
+            .queue_depth(self.queue_depth)
+            .io_backend(self.io_backend);
+        let (checker, sample) = self.checker(&engine);
+        let total = checker.extent();
+        invalidate_cache(target, path)?;
+        let direct = direct_reader(target, path)?;
+
+        on_event(&Event::VerifyStarted { pass: passes });
+        let mut data = fill.data(&self.secret, passes);
+        let bytes_verified = checker
+            .verify_pass(
+                direct.as_deref().unwrap_or(target),
+                &mut data,
+                &BadSectors::default(),
+                |read| {
+                    on_event(&Event::VerifyProgress {
+                        pass: passes,
+                        read,
+                        total,
+                    })
+                },
+            )
+            .map_err(|e| in_pass(e, passes))?;
+
+        Ok(WipeOutcome {
+            target: self.target.clone(),
+            method: self.method.clone(),
+            geometry,
+            passes: 0,
+            bytes_per_pass: geometry.size,
+            bytes_written: 0,
+            bytes_verified,
+            verification: Verification::Passed,
+            sample,
+            identity: None,
+            dry_run: false,
+            resumed: None,
+            bad_sectors: Vec::new(),
+            direct_io: false,
+            io_backend: checker.used_backend(),
+        })
+    }
+}
@@ -0,0 +1,51 @@
+//! Securely wipe block devices.
+//!
+//! The library never prints or exits; progress is reported through the
+//! callback passed to [`WipeJob::run`] and failures are returned as [`Error`].
+
+mod bad_sectors;
+mod buffer;
+mod checkpoint;
+mod device;
+mod discovery;
+mod engine;
+mod error;
+mod identity;
+mod job;
+mod lock;
+mod method;
+mod method_file;
+mod policy;
+mod probe;
+mod report;
+mod rng;
+mod sample;
+mod selector;
+mod sys;
+mod sysfs;
+mod target;
+#[cfg(feature = "io-uring")]
+mod uring;
+mod usage;
+
+pub use bad_sectors::BadSectorPolicy;
+pub use checkpoint::{Checkpoint, Phase, Resumed, RngPosition, DEFAULT_JOURNAL_DIR};
+pub use device::{geometry, is_drive_in_use, open_holders, Geometry, Process};
+pub use discovery::{Drive, PartitionOf, Transport};
+pub use error::{Error, Result};
+pub use identity::Identity;
+pub use job::{
+    Estimate, Event, IoBackend, JobStatus, SparseMode, Verification, VerifyPolicy, WipeJob,
+    WipeOutcome, DEFAULT_QUEUE_DEPTH,
+};
+pub use lock::TargetLock;
+pub use method::{Pass, Pattern, Standard, WipeMethod, STANDARDS};
+pub use policy::{Policy, Rules, SystemDisk, DEFAULT_POLICY_PATH};
+pub use probe::{PartitionPreview, PartitionTable, Preview, Signature};
+pub use report::{DeviceReport, Report};
+pub use rng::RunSecret;
+pub use sample::{Sample, Sampling};
+pub use selector::Selector;
+pub use sysfs::{BlockDev, DevId, SysRoot};
+pub use target::{BlockDevice, BlockTarget, FaultyTarget, MemoryTarget, RegularFile};
+pub use usage::{blockers, is_drive_mounted, unmount_drive, Blocker, Mount, Reason};
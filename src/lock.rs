@@ -0,0 +1,110 @@
+//! Keeps a target to a single writer: `O_EXCL` against the kernel and other
+//! tools, and an advisory lock file against other wipers runs.
+
+use crate::device::open_holders;
+use crate::error::{Error, Result};
+use crate::sysfs::DevId;
+use crate::usage::blockers;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the per-device lock files, if it exists.
+const LOCK_DIR: &str = "/run/lock";
+
+/// An advisory lock on a target, released when dropped.
+#[derive(Debug)]
+pub struct TargetLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl TargetLock {
+    /// Path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Takes the lock for `target`, keyed by device number so that every
+    /// path naming the same disk shares one lock.
+    pub fn acquire(target: &Path) -> Result<TargetLock> {
+        let metadata = fs::metadata(target).map_err(|e| Error::io(target, "stat", e))?;
+        let name = if metadata.file_type().is_block_device() {
+            let dev = DevId::from_raw(metadata.rdev());
+            format!("wipers-{}_{}.lock", dev.major, dev.minor)
+        } else {
+            format!("wipers-file-{}-{}.lock", metadata.dev(), metadata.ino())
+        };
+        let dir = if Path::new(LOCK_DIR).is_dir() {
+            PathBuf::from(LOCK_DIR)
+        } else {
+            std::env::temp_dir()
+        };
+        let path = dir.join(name);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o644)
+            .open(&path)
+            .map_err(|e| Error::io(&path, "open", e))?;
+
+        // SAFETY: flock only operates on the descriptor, which `file` keeps open.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::WouldBlock {
+                return Err(Error::io(&path, "lock", err));
+            }
+            let owner = match fs::read_to_string(&path).map(|pid| pid.trim().to_string()) {
+                Ok(pid) if !pid.is_empty() => format!("another wipers run (pid {})", pid),
+                _ => "another wipers run".to_string(),
+            };
+            return Err(Error::Busy {
+                path: target.to_path_buf(),
+                users: vec![owner],
+            });
+        }
+
+        file.set_len(0)
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .map_err(|e| Error::io(&path, "write", e))?;
+        Ok(TargetLock { _file: file, path })
+    }
+}
+
+/// Opens `target` for reading, and for writing unless `read_only`. Block
+/// devices are opened with `O_EXCL`, which the kernel refuses while the
+/// device is mounted, stacked on, or exclusively opened elsewhere.
+pub(crate) fn open_exclusive(target: &Path, read_only: bool) -> Result<File> {
+    let metadata = fs::metadata(target).map_err(|e| Error::io(target, "stat", e))?;
+    let mut options = OpenOptions::new();
+    options.read(true).write(!read_only);
+    if metadata.file_type().is_block_device() {
+        options.custom_flags(libc::O_EXCL);
+    }
+
+    options.open(target).map_err(|e| {
+        if e.raw_os_error() != Some(libc::EBUSY) {
+            return Error::io(target, "open", e);
+        }
+        let mut users: Vec<String> = open_holders(target)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| format!("{} (pid {})", p.command, p.pid))
+            .collect();
+        users.extend(
+            blockers(target)
+                .unwrap_or_default()
+                .iter()
+                .map(ToString::to_string),
+        );
+        Error::Busy {
+            path: target.to_path_buf(),
+            users,
+        }
+    })
+}
@@ -0,0 +1,382 @@
+use crate::checkpoint::RngPosition;
+use crate::error::{Error, Result};
+use crate::rng::{self, RunSecret};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The data written by a single pass.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pattern {
+    /// Every byte set to the same value.
+    Byte(u8),
+    /// A multi-byte sequence repeated across the device, anchored at offset 0.
+    Repeat(Vec<u8>),
+    /// The bitwise complement of the previous pass.
+    Complement,
+    /// Random data.
+    Random,
+    /// Every 512-byte sector filled with its own little-endian LBA.
+    LbaStamp,
+}
+
+/// Size of the sectors addressed by [`Pattern::LbaStamp`].
+pub(crate) const LBA_SIZE: u64 = 512;
+
+/// A pass pattern with complements resolved away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Fill {
+    /// Never [`Pattern::Complement`].
+    pub pattern: Pattern,
+    /// Whether the pattern's bytes are inverted.
+    pub invert: bool,
+    /// How many passes back the pattern was defined; random data comes from
+    /// that pass's stream.
+    pub back: u32,
+}
+
+/// Produces the bytes of one pass at arbitrary offsets.
+#[derive(Clone)]
+pub(crate) struct PassData<'a> {
+    fill: &'a Fill,
+    rng: Option<ChaCha20Rng>,
+}
+
+/// One overwrite of the whole device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pass {
+    pub pattern: Pattern,
+    /// Read the device back after this pass and compare it to the pattern.
+    pub verify: bool,
+}
+
+/// A named sequence of passes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WipeMethod {
+    pub name: String,
+    pub passes: Vec<Pass>,
+}
+
+/// A recognized sanitization standard from the built-in table.
+pub struct Standard {
+    /// Short identifier accepted by `--method`.
+    pub id: &'static str,
+    /// Full name shown in output.
+    pub name: &'static str,
+    build: fn() -> Vec<Pass>,
+}
+
+impl Standard {
+    pub fn method(&self) -> WipeMethod {
+        WipeMethod {
+            name: self.name.to_string(),
+            passes: (self.build)(),
+        }
+    }
+}
+
+fn byte(value: u8) -> Pass {
+    Pass {
+        pattern: Pattern::Byte(value),
+        verify: false,
+    }
+}
+
+fn repeat(bytes: &[u8]) -> Pass {
+    Pass {
+        pattern: Pattern::Repeat(bytes.to_vec()),
+        verify: false,
+    }
+}
+
+fn complement() -> Pass {
+    Pass {
+        pattern: Pattern::Complement,
+        verify: false,
+    }
+}
+
+fn random() -> Pass {
+    Pass {
+        pattern: Pattern::Random,
+        verify: false,
+    }
+}
+
+fn verified(pass: Pass) -> Pass {
+    Pass {
+        verify: true,
+        ..pass
+    }
+}
+
+fn dod_5220_22_m() -> Vec<Pass> {
+    vec![byte(0x00), complement(), verified(random())]
+}
+
+fn gutmann() -> Vec<Pass> {
+    let mut passes: Vec<Pass> = (0..4).map(|_| random()).collect();
+    passes.push(byte(0x55));
+    passes.push(byte(0xAA));
+    passes.push(repeat(&[0x92, 0x49, 0x24]));
+    passes.push(repeat(&[0x49, 0x24, 0x92]));
+    passes.push(repeat(&[0x24, 0x92, 0x49]));
+    passes.extend((0..=0xF).map(|n| byte(n * 0x11)));
+    passes.push(repeat(&[0x92, 0x49, 0x24]));
+    passes.push(repeat(&[0x49, 0x24, 0x92]));
+    passes.push(repeat(&[0x24, 0x92, 0x49]));
+    passes.push(repeat(&[0x6D, 0xB6, 0xDB]));
+    passes.push(repeat(&[0xB6, 0xDB, 0x6D]));
+    passes.push(repeat(&[0xDB, 0x6D, 0xB6]));
+    passes.extend((0..4).map(|_| random()));
+    passes
+}
+
+fn schneier() -> Vec<Pass> {
+    let mut passes = vec![byte(0xFF), byte(0x00)];
+    passes.extend((0..5).map(|_| random()));
+    passes
+}
+
+fn vsitr() -> Vec<Pass> {
+    vec![
+        byte(0x00),
+        byte(0xFF),
+        byte(0x00),
+        byte(0xFF),
+        byte(0x00),
+        byte(0xFF),
+        verified(byte(0xAA)),
+    ]
+}
+
+fn hmg_is5_baseline() -> Vec<Pass> {
+    vec![verified(byte(0x00))]
+}
+
+fn hmg_is5_enhanced() -> Vec<Pass> {
+    vec![byte(0x00), byte(0xFF), verified(random())]
+}
+
+fn rcmp_tssit_ops_ii() -> Vec<Pass> {
+    vec![
+        byte(0x00),
+        byte(0xFF),
+        byte(0x00),
+        byte(0xFF),
+        byte(0x00),
+        byte(0xFF),
+        verified(random()),
+    ]
+}
+
+/// Built-in methods selectable by id.
+pub static STANDARDS: &[Standard] = &[
+    Standard {
+        id: "zero",
+        name: "Zero fill",
+        build: || vec![byte(0x00)],
+    },
+    Standard {
+        id: "random",
+        name: "Random fill",
+        build: || vec![random()],
+    },
+    Standard {
+        id: "dod",
+        name: "DoD 5220.22-M",
+        build: dod_5220_22_m,
+    },
+    Standard {
+        id: "gutmann",
+        name: "Gutmann",
+        build: gutmann,
+    },
+    Standard {
+        id: "schneier",
+        name: "Bruce Schneier",
+        build: schneier,
+    },
+    Standard {
+        id: "vsitr",
+        name: "BSI VSITR",
+        build: vsitr,
+    },
+    Standard {
+        id: "hmg-is5-baseline",
+        name: "HMG IS5 Baseline",
+        build: hmg_is5_baseline,
+    },
+    Standard {
+        id: "hmg-is5-enhanced",
+        name: "HMG IS5 Enhanced",
+        build: hmg_is5_enhanced,
+    },
+    Standard {
+        id: "rcmp-tssit-ops-ii",
+        name: "RCMP TSSIT OPS-II",
+        build: rcmp_tssit_ops_ii,
+    },
+];
+
+impl WipeMethod {
+    /// Looks up a built-in standard by id, ignoring case.
+    pub fn standard(id: &str) -> Result<WipeMethod> {
+        STANDARDS
+            .iter()
+            .find(|s| s.id.eq_ignore_ascii_case(id))
+            .map(Standard::method)
+            .ok_or_else(|| {
+                let known: Vec<_> = STANDARDS.iter().map(|s| s.id).collect();
+                Error::InvalidMethod(format!(
+                    "unknown method '{}' (expected one of: {})",
+                    id,
+                    known.join(", ")
+                ))
+            })
+    }
+
+    /// A single pass of zeros.
+    pub fn zero() -> WipeMethod {
+        STANDARDS[0].method()
+    }
+
+    /// A single pass of random data.
+    pub fn random() -> WipeMethod {
+        STANDARDS[1].method()
+    }
+
+    /// Checks that the method can be executed.
+    pub fn validate(&self) -> Result<()> {
+        if self.passes.is_empty() {
+            return Err(Error::InvalidMethod(format!(
+                "method '{}' has no passes",
+                self.name
+            )));
+        }
+        self.resolve().map(|_| ())
+    }
+
+    /// True if the final pass writes (possibly inverted) random data, which
+    /// can only be verified with the run's secret.
+    pub fn ends_with_random(&self) -> bool {
+        self.resolve()
+            .ok()
+            .and_then(|fills| fills.last().map(|f| f.pattern == Pattern::Random))
+            .unwrap_or(false)
+    }
+
+    /// Replaces every complement pass with the pattern it inverts.
+    pub(crate) fn resolve(&self) -> Result<Vec<Fill>> {
+        let mut resolved: Vec<Fill> = Vec::with_capacity(self.passes.len());
+        for (i, pass) in self.passes.iter().enumerate() {
+            let fill = match &pass.pattern {
+                Pattern::Complement => match resolved.last() {
+                    Some(previous) => Fill {
+                        pattern: previous.pattern.clone(),
+                        invert: !previous.invert,
+                        back: previous.back + 1,
+                    },
+                    None => {
+                        return Err(Error::InvalidMethod(format!(
+                            "pass 1 of '{}': a complement needs a previous pass",
+                            self.name
+                        )))
+                    }
+                },
+                Pattern::Repeat(bytes) if bytes.is_empty() => {
+                    return Err(Error::InvalidMethod(format!(
+                        "pass {} of '{}': empty pattern",
+                        i + 1,
+                        self.name
+                    )))
+                }
+                other => Fill {
+                    pattern: other.clone(),
+                    invert: false,
+                    back: 0,
+                },
+            };
+            resolved.push(fill);
+        }
+        Ok(resolved)
+    }
+}
+
+impl Fill {
+    /// The data source for global pass number `pass` of a run keyed by `secret`.
+    pub(crate) fn data<'a>(&'a self, secret: &RunSecret, pass: u32) -> PassData<'a> {
+        let rng = match self.pattern {
+            Pattern::Random => Some(secret.stream(pass - self.back)),
+            _ => None,
+        };
+        PassData { fill: self, rng }
+    }
+}
+
+impl PassData<'_> {
+    /// Where this pass's random stream stands at byte `offset`; `None` for
+    /// passes that are not random.
+    pub(crate) fn rng_position(&self, offset: u64) -> Option<RngPosition> {
+        self.rng.as_ref().map(|rng| RngPosition {
+            stream: rng.get_stream(),
+            word: offset / 4,
+        })
+    }
+
+    /// Fills `buf` with the data this pass places at byte `offset`.
+    pub(crate) fn fill(&mut self, offset: u64, buf: &mut [u8]) {
+        match &self.fill.pattern {
+            Pattern::Byte(b) => buf.fill(*b),
+            Pattern::Repeat(bytes) => {
+                let len = bytes.len();
+                let mut phase = (offset % len as u64) as usize;
+                for byte in buf.iter_mut() {
+                    *byte = bytes[phase];
+                    phase = (phase + 1) % len;
+                }
+            }
+            Pattern::LbaStamp => {
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    let pos = offset + i as u64;
+                    let lba = (pos / LBA_SIZE).to_le_bytes();
+                    *byte = lba[(pos % 8) as usize];
+                }
+            }
+            Pattern::Random => {
+                let rng = self.rng.as_mut().expect("random pass without a stream");
+                rng::fill_at(rng, offset, buf);
+            }
+            Pattern::Complement => unreachable!("pattern must be resolved before filling"),
+        }
+        if self.fill.invert {
+            buf.iter_mut().for_each(|b| *b = !*b);
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Byte(b) => write!(f, "0x{:02X}", b),
+            Pattern::Repeat(bytes) => {
+                let hex: Vec<_> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                write!(f, "0x{}", hex.join(""))
+            }
+            Pattern::Complement => write!(f, "complement"),
+            Pattern::Random => write!(f, "random"),
+            Pattern::LbaStamp => write!(f, "LBA stamp"),
+        }
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        if self.verify {
+            write!(f, ", verify")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+//! Custom wipe methods loaded from TOML.
+//!
+//! ```toml
+//! name = "In-house 5"
+//!
+//! [[pass]]
+//! type = "random"
+//!
+//! [[pass]]
+//! type = "byte"
+//! value = 0x55
+//!
+//! [[pass]]
+//! type = "byte"
+//! value = 0xAA
+//! verify = true
+//!
+//! [[pass]]
+//! type = "byte"
+//! value = 0x00
+//! verify = true
+//! ```
+
+use crate::error::{Error, Result};
+use crate::method::{Pass, Pattern, WipeMethod};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MethodFile {
+    name: String,
+    #[serde(default, rename = "pass")]
+    passes: Vec<PassDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PassDef {
+    #[serde(rename = "type")]
+    kind: Kind,
+    value: Option<u8>,
+    bytes: Option<Vec<u8>>,
+    #[serde(default)]
+    verify: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Kind {
+    Byte,
+    Pattern,
+    Complement,
+    Random,
+    Lba,
+}
+
+impl PassDef {
+    fn into_pass(self, n: usize) -> Result<Pass> {
+        let invalid = |msg: &str| Error::InvalidMethod(format!("pass {}: {}", n, msg));
+        let pattern = match (self.kind, self.value, self.bytes) {
+            (Kind::Byte, Some(value), None) => Pattern::Byte(value),
+            (Kind::Byte, None, _) => return Err(invalid("type 'byte' requires 'value'")),
+            (Kind::Pattern, None, Some(bytes)) if !bytes.is_empty() => Pattern::Repeat(bytes),
+            (Kind::Pattern, None, _) => {
+                return Err(invalid("type 'pattern' requires a non-empty 'bytes' list"))
+            }
+            (Kind::Complement, None, None) => Pattern::Complement,
+            (Kind::Random, None, None) => Pattern::Random,
+            (Kind::Lba, None, None) => Pattern::LbaStamp,
+            (_, Some(_), _) => return Err(invalid("'value' is only allowed with type 'byte'")),
+            (_, _, Some(_)) => return Err(invalid("'bytes' is only allowed with type 'pattern'")),
+        };
+        Ok(Pass {
+            pattern,
+            verify: self.verify,
+        })
+    }
+}
+
+impl WipeMethod {
+    /// Parses a method definition from TOML and validates it.
+    pub fn from_toml(source: &str) -> Result<WipeMethod> {
+        let file: MethodFile =
+            toml::from_str(source).map_err(|e| Error::InvalidMethod(e.to_string()))?;
+        if file.name.trim().is_empty() {
+            return Err(Error::InvalidMethod("'name' must not be empty".into()));
+        }
+
+        let passes = file
+            .passes
+            .into_iter()
+            .enumerate()
+            .map(|(i, def)| def.into_pass(i + 1))
+            .collect::<Result<Vec<_>>>()?;
+        let method = WipeMethod {
+            name: file.name,
+            passes,
+        };
+        method.validate()?;
+        Ok(method)
+    }
+
+    /// Reads and validates a method definition from a TOML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<WipeMethod> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| Error::io(path, "read", e))?;
+        WipeMethod::from_toml(&source).map_err(|e| match e {
+            Error::InvalidMethod(msg) => {
+                Error::InvalidMethod(format!("{}: {}", path.display(), msg))
+            }
+            other => other,
+        })
+    }
+}
@@ -0,0 +1,288 @@
+//! Decides which drives may be wiped: the running system's own disks are
+//! always protected, and a policy file can allow or deny drives by identity.
+
+use crate::discovery::Drive;
+use crate::error::{Error, Result};
+use crate::sysfs::{DevId, SysRoot};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the policy is read from when none is given explicitly.
+pub const DEFAULT_POLICY_PATH: &str = "/etc/wipers/policy.toml";
+
+/// Mount points whose backing disks keep the running system alive. The
+/// initramfs mounts the real root at `/sysroot` (dracut) or `/root`
+/// (initramfs-tools) before switching to it.
+const SYSTEM_MOUNTS: &[&str] = &["/", "/boot", "/boot/efi", "/efi", "/sysroot"];
+
+/// A whole disk the running system depends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemDisk {
+    pub dev: DevId,
+    /// Kernel name of the disk.
+    pub name: String,
+    /// What on the disk the system uses, such as `/boot (sda1)`.
+    pub uses: Vec<String>,
+}
+
+/// Matches `text` against a glob where `*` matches any run of characters and
+/// `?` matches one character.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut backtrack = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((bp, bt)) = backtrack {
+            pi = bp + 1;
+            ti = bt + 1;
+            backtrack = Some((bp, bt + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+impl SysRoot {
+    /// Whole disks and their partitions, as `(disk, partition numbers)`.
+    fn disk_table(&self) -> Result<Vec<(String, DevId, Vec<DevId>)>> {
+        let dir = self.sys_path("block");
+        let entries = fs::read_dir(&dir).map_err(|e| Error::io(&dir, "read", e))?;
+        let mut disks = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(dev) = self
+                .attr(&entry.path().join("dev"))
+                .and_then(|d| d.parse().ok())
+            {
+                let parts = self.partitions(dev)?.iter().map(|p| p.dev).collect();
+                disks.push((name, dev, parts));
+            }
+        }
+        Ok(disks)
+    }
+
+    /// The whole disks that `dev` ultimately lives on, following partitions
+    /// to their disk and device-mapper or md devices to their members.
+    fn backing_disks(
+        &self,
+        dev: DevId,
+        table: &[(String, DevId, Vec<DevId>)],
+        seen: &mut HashSet<DevId>,
+    ) -> Vec<(String, DevId)> {
+        if !seen.insert(dev) {
+            return Vec::new();
+        }
+        if let Some((name, disk, _)) = table
+            .iter()
+            .find(|(_, disk, parts)| *disk == dev || parts.contains(&dev))
+        {
+            let slaves = self.slaves(*disk);
+            if slaves.is_empty() {
+                return vec![(name.clone(), *disk)];
+            }
+        }
+        self.slaves(dev)
+            .into_iter()
+            .flat_map(|slave| self.backing_disks(slave.dev, table, seen))
+            .collect()
+    }
+
+    /// The `root=` device from the kernel command line, if it can be resolved.
+    fn cmdline_root(&self) -> Option<DevId> {
+        let cmdline = fs::read_to_string(self.proc_path("cmdline")).ok()?;
+        let spec = cmdline
+            .split_whitespace()
+            .find_map(|arg| arg.strip_prefix("root="))?;
+        let path = if let Some(uuid) = spec.strip_prefix("UUID=") {
+            self.dev_path("disk/by-uuid").join(uuid)
+        } else if let Some(uuid) = spec.strip_prefix("PARTUUID=") {
+            self.dev_path("disk/by-partuuid").join(uuid)
+        } else if let Some(label) = spec.strip_prefix("LABEL=") {
+            self.dev_path("disk/by-label").join(label)
+        } else {
+            PathBuf::from(spec)
+        };
+        self.by_path(&path).map(|b| b.dev)
+    }
+
+    /// Disks backing `/`, `/boot`, `/boot/efi`, the initramfs root, the
+    /// kernel's `root=` device and active swap.
+    pub fn system_disks(&self) -> Result<Vec<SystemDisk>> {
+        let table = self.disk_table()?;
+        let mounts = self.mounts()?;
+        // In an initramfs, `/` is a tmpfs or rootfs with no backing device.
+        let in_initramfs = mounts
+            .iter()
+            .any(|m| m.mount_point == Path::new("/") && m.dev.major == 0);
+        let mut uses: Vec<(DevId, String)> = Vec::new();
+
+        for mount in mounts {
+            let point = mount.mount_point.to_string_lossy();
+            if SYSTEM_MOUNTS.contains(&point.as_ref()) || (in_initramfs && point == "/root") {
+                uses.push((mount.dev, point.into_owned()));
+            }
+        }
+        for swap in self.swaps()? {
+            uses.push((swap.dev, "swap".to_string()));
+        }
+        if let Some(root) = self.cmdline_root() {
+            uses.push((root, "kernel root=".to_string()));
+        }
+
+        let mut disks: Vec<SystemDisk> = Vec::new();
+        for (dev, what) in uses {
+            let label = match self.name_of(dev) {
+                Ok(name) => format!("{} ({})", what, name),
+                Err(_) => what,
+            };
+            for (name, disk) in self.backing_disks(dev, &table, &mut HashSet::new()) {
+                match disks.iter_mut().find(|d| d.dev == disk) {
+                    Some(existing) if !existing.uses.contains(&label) => {
+                        existing.uses.push(label.clone())
+                    }
+                    Some(_) => {}
+                    None => disks.push(SystemDisk {
+                        dev: disk,
+                        name,
+                        uses: vec![label.clone()],
+                    }),
+                }
+            }
+        }
+        disks.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(disks)
+    }
+}
+
+/// Drive identities matched by a policy section. Every entry is a glob.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Rules {
+    pub serial: Vec<String>,
+    pub wwn: Vec<String>,
+    pub model: Vec<String>,
+    pub by_id: Vec<String>,
+}
+
+impl Rules {
+    pub fn is_empty(&self) -> bool {
+        self.serial.is_empty()
+            && self.wwn.is_empty()
+            && self.model.is_empty()
+            && self.by_id.is_empty()
+    }
+
+    /// Describes the first rule that matches `drive`.
+    pub fn matching(&self, drive: &Drive) -> Option<String> {
+        let field = |rules: &[String], value: Option<&str>, what: &str| {
+            let value = value?;
+            rules
+                .iter()
+                .find(|rule| glob_match(rule, value))
+                .map(|rule| format!("{} '{}'", what, rule))
+        };
+        field(&self.serial, drive.serial.as_deref(), "serial")
+            .or_else(|| {
+                let wwn = drive.wwn.as_deref().map(str::to_ascii_lowercase);
+                let rules: Vec<String> = self.wwn.iter().map(|w| w.to_ascii_lowercase()).collect();
+                field(&rules, wwn.as_deref(), "wwn")
+            })
+            .or_else(|| field(&self.model, drive.model.as_deref(), "model"))
+            .or_else(|| {
+                drive
+                    .by_id
+                    .iter()
+                    .find_map(|link| field(&self.by_id, link.to_str(), "by-id"))
+            })
+    }
+}
+
+/// Allow and deny rules for drives, loaded from TOML:
+///
+/// ```
+/// use wipers::Policy;
+///
+/// let policy = Policy::from_toml(
+///     r#"
+///     ## Allow `--yes` to skip the confirmation prompt.
+///     unattended = true
+///
+///     [deny]
+///     serial = ["WD-WCC4N0000001"]
+///     model = ["Samsung SSD 990*"]
+///
+///     ## When present, only drives matching one of these may be wiped.
+///     [allow]
+///     model = ["ST4000*"]
+///     "#,
+/// )?;
+/// assert!(policy.unattended);
+/// # Ok::<(), wipers::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// If non-empty, only drives matching one of these rules may be wiped.
+    pub allow: Rules,
+    /// Drives matching any of these rules are never wiped.
+    pub deny: Rules,
+    /// Lets `--yes` skip the typed confirmation, for automated stations.
+    pub unattended: bool,
+}
+
+impl Policy {
+    pub fn from_toml(source: &str) -> Result<Policy> {
+        toml::from_str(source).map_err(|e| Error::InvalidPolicy(e.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Policy> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| Error::io(path, "read", e))?;
+        toml::from_str(&source)
+            .map_err(|e| Error::InvalidPolicy(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Loads `DEFAULT_POLICY_PATH`, or an empty policy if it does not exist.
+    pub fn load_default() -> Result<Policy> {
+        if Path::new(DEFAULT_POLICY_PATH).exists() {
+            Policy::load(DEFAULT_POLICY_PATH)
+        } else {
+            Ok(Policy::default())
+        }
+    }
+
+    /// Refuses system disks and their partitions, unless the disk's kernel
+    /// name is listed in `overrides`, and drives the allow and deny rules
+    /// exclude.
+    pub fn check(&self, drive: &Drive, system: &[SystemDisk], overrides: &[String]) -> Result<()> {
+        let refuse = |reason: String| Error::Protected {
+            path: drive.path.clone(),
+            reason,
+        };
+        if let Some(disk) = system.iter().find(|d| d.dev == drive.disk_dev()) {
+            if !overrides.iter().any(|o| o == drive.disk_name()) {
+                return Err(refuse(format!(
+                    "it is a system disk backing {}",
+                    disk.uses.join(", ")
+                )));
+            }
+        }
+        if let Some(rule) = self.deny.matching(drive) {
+            return Err(refuse(format!("denied by policy rule {}", rule)));
+        }
+        if !self.allow.is_empty() && self.allow.matching(drive).is_none() {
+            return Err(refuse("not matched by any allow rule in the policy".into()));
+        }
+        Ok(())
+    }
+}
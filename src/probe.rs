@@ -0,0 +1,272 @@
+//! Read-only look at what a target holds before it is destroyed: partition
+//! tables, filesystems and their labels, and LVM, md RAID or LUKS members.
+
+use crate::device;
+use crate::discovery::Drive;
+use crate::error::{Error, Result};
+use crate::sysfs::{DevId, SysRoot};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+/// The kind of partition table at the start of a disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionTable {
+    Gpt,
+    Mbr,
+}
+
+/// A filesystem or volume-manager signature, named like `blkid` names them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    /// `ext4`, `xfs`, `btrfs`, `vfat`, `ntfs`, `exfat`, `iso9660`, `swap`,
+    /// `crypto_LUKS`, `LVM2_member` or `linux_raid_member`.
+    pub kind: String,
+    pub label: Option<String>,
+}
+
+impl Signature {
+    /// True for LVM physical volumes, md RAID members and LUKS containers,
+    /// whose loss takes down whatever is stacked on them.
+    pub fn is_member(&self) -> bool {
+        matches!(
+            self.kind.as_str(),
+            "LVM2_member" | "linux_raid_member" | "crypto_LUKS"
+        )
+    }
+}
+
+/// One partition of a previewed disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionPreview {
+    pub name: String,
+    pub size: u64,
+    pub signature: Option<Signature>,
+    /// Devices stacked on the partition, such as `dm-0 (vg-root)`.
+    pub holders: Vec<String>,
+}
+
+/// Everything about to be destroyed on one target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preview {
+    pub target: PathBuf,
+    /// The drive's identity; absent for image files.
+    pub drive: Option<Drive>,
+    pub size: u64,
+    pub table: Option<PartitionTable>,
+    /// A signature on the whole target rather than in a partition.
+    pub signature: Option<Signature>,
+    /// Devices stacked on the whole disk.
+    pub holders: Vec<String>,
+    pub partitions: Vec<PartitionPreview>,
+}
+
+/// Reads `len` bytes at `offset`, or `None` past the end of the target.
+fn read(file: &File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    file.read_exact_at(&mut buf, offset).ok()?;
+    Some(buf)
+}
+
+fn le16(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn le32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+/// A NUL- or space-padded label, or `None` if blank.
+fn label(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let text = String::from_utf8_lossy(&bytes[..end]);
+    let text = text.trim();
+    (!text.is_empty() && text != "NO NAME").then(|| text.to_string())
+}
+
+fn found(kind: &str, label: Option<String>) -> Option<Signature> {
+    Some(Signature {
+        kind: kind.to_string(),
+        label,
+    })
+}
+
+fn luks(file: &File) -> Option<Signature> {
+    let header = read(file, 0, 72)?;
+    if &header[..6] != b"LUKS\xba\xbe" {
+        return None;
+    }
+    // Only LUKS2 headers carry a label.
+    let version = u16::from_be_bytes([header[6], header[7]]);
+    found(
+        "crypto_LUKS",
+        (version == 2).then(|| label(&header[24..72])).flatten(),
+    )
+}
+
+fn lvm(file: &File) -> Option<Signature> {
+    // The label may sit in any of the first four sectors.
+    (0..4).find_map(|sector| {
+        let buf = read(file, sector * 512, 32)?;
+        (&buf[..8] == b"LABELONE" && &buf[24..32] == b"LVM2 001").then_some(())?;
+        found("LVM2_member", None)
+    })
+}
+
+fn md_raid(file: &File, size: u64) -> Option<Signature> {
+    const MAGIC: u32 = 0xa92b_4efc;
+    // Version 1.1 and 1.2 superblocks sit at 0 and 4 KiB, version 1.0 8 KiB
+    // before the end, and 0.90 in the last 64 KiB-aligned block.
+    let v1_end = size.checked_sub(8192).map(|o| o & !4095);
+    let v090 = (size & !65535).checked_sub(65536);
+    for offset in [Some(0), Some(4096), v1_end].into_iter().flatten() {
+        if let Some(sb) = read(file, offset, 64) {
+            if le32(&sb, 0) == MAGIC && le32(&sb, 4) == 1 {
+                return found("linux_raid_member", label(&sb[32..64]));
+            }
+        }
+    }
+    let sb = read(file, v090?, 4)?;
+    (le32(&sb, 0) == MAGIC).then(|| found("linux_raid_member", None))?
+}
+
+fn ext(file: &File) -> Option<Signature> {
+    let sb = read(file, 1024, 136)?;
+    if le16(&sb, 56) != 0xef53 {
+        return None;
+    }
+    let compat = le32(&sb, 92);
+    let incompat = le32(&sb, 96);
+    let ro_compat = le32(&sb, 100);
+    // Extents, 64-bit, flex_bg or huge files only exist from ext4 on.
+    let kind = if incompat & 0x2c0 != 0 || ro_compat & 0x8 != 0 {
+        "ext4"
+    } else if compat & 0x4 != 0 {
+        "ext3"
+    } else {
+        "ext2"
+    };
+    found(kind, label(&sb[120..136]))
+}
+
+fn xfs(file: &File) -> Option<Signature> {
+    let sb = read(file, 0, 120)?;
+    (&sb[..4] == b"XFSB").then(|| found("xfs", label(&sb[108..120])))?
+}
+
+fn btrfs(file: &File) -> Option<Signature> {
+    let sb = read(file, 65536, 555)?;
+    (&sb[64..72] == b"_BHRfS_M").then(|| found("btrfs", label(&sb[299..555])))?
+}
+
+fn swap(file: &File) -> Option<Signature> {
+    // The signature ends the first page; try the common page sizes.
+    [4096u64, 8192, 16384, 65536].into_iter().find_map(|page| {
+        let magic = read(file, page - 10, 10)?;
+        if &magic != b"SWAPSPACE2" && &magic != b"SWAP-SPACE" {
+            return None;
+        }
+        let header = read(file, 1024, 44)?;
+        found("swap", label(&header[28..44]))
+    })
+}
+
+fn boot_sector(file: &File) -> Option<Signature> {
+    let bs = read(file, 0, 512)?;
+    if &bs[3..11] == b"NTFS    " {
+        return found("ntfs", None);
+    }
+    if &bs[3..11] == b"EXFAT   " {
+        return found("exfat", None);
+    }
+    if bs[510..512] != [0x55, 0xaa] {
+        return None;
+    }
+    if &bs[82..87] == b"FAT32" {
+        return found("vfat", label(&bs[71..82]));
+    }
+    if &bs[54..59] == b"FAT12" || &bs[54..59] == b"FAT16" {
+        return found("vfat", label(&bs[43..54]));
+    }
+    None
+}
+
+fn iso9660(file: &File) -> Option<Signature> {
+    let pvd = read(file, 32768, 72)?;
+    (&pvd[1..6] == b"CD001").then(|| found("iso9660", label(&pvd[40..72])))?
+}
+
+/// Identifies the filesystem or volume-manager signature on `file`, whose
+/// size is `size` bytes.
+fn signature(file: &File, size: u64) -> Option<Signature> {
+    luks(file)
+        .or_else(|| lvm(file))
+        .or_else(|| md_raid(file, size))
+        .or_else(|| xfs(file))
+        .or_else(|| ext(file))
+        .or_else(|| btrfs(file))
+        .or_else(|| swap(file))
+        .or_else(|| boot_sector(file))
+        .or_else(|| iso9660(file))
+}
+
+/// Identifies the partition table on `file`, whose logical sectors are
+/// `sector_size` bytes.
+fn partition_table(file: &File, sector_size: u32) -> Option<PartitionTable> {
+    if let Some(header) = read(file, sector_size.max(512) as u64, 8) {
+        if &header == b"EFI PART" {
+            return Some(PartitionTable::Gpt);
+        }
+    }
+    let mbr = read(file, 0, 512)?;
+    // A FAT or NTFS boot sector ends in the same marker as an MBR.
+    (mbr[510..512] == [0x55, 0xaa] && boot_sector(file).is_none()).then_some(PartitionTable::Mbr)
+}
+
+impl SysRoot {
+    fn holder_names(&self, dev: DevId) -> Vec<String> {
+        self.holders(dev)
+            .iter()
+            .map(|holder| self.display_name(holder))
+            .collect()
+    }
+
+    /// Reads what `target` holds without modifying it. Partitions are the
+    /// ones the kernel knows about, opened through `dev/<name>`.
+    pub fn preview(&self, target: &Path, drive: Option<&Drive>) -> Result<Preview> {
+        let open = |path: &Path| File::open(path).map_err(|e| Error::io(path, "open", e));
+        let file = open(target)?;
+        let geometry = device::geometry(&file, target)?;
+
+        let mut preview = Preview {
+            target: target.to_path_buf(),
+            drive: drive.cloned(),
+            size: geometry.size,
+            table: partition_table(&file, geometry.logical_sector_size),
+            signature: signature(&file, geometry.size),
+            holders: Vec::new(),
+            partitions: Vec::new(),
+        };
+        let Some(drive) = drive else {
+            return Ok(preview);
+        };
+        preview.holders = self.holder_names(drive.dev);
+        for part in self.partitions(drive.dev)? {
+            let size = self
+                .attr(&self.block_dir(part.dev).join("size"))
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(0)
+                * 512;
+            let file = open(&self.dev_path(&part.name))?;
+            preview.partitions.push(PartitionPreview {
+                signature: signature(&file, size),
+                holders: self.holder_names(part.dev),
+                name: part.name,
+                size,
+            });
+        }
+        Ok(preview)
+    }
+}
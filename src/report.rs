@@ -0,0 +1,185 @@
+//! Machine-readable record of a wipe run.
+
+use crate::checkpoint::Resumed;
+use crate::discovery::Drive;
+use crate::error::{Error, Result};
+use crate::job::{IoBackend, JobStatus, Verification};
+use crate::method::WipeMethod;
+use crate::sample::Sample;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A wipe run across one or more devices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// Version of wipers that produced the report.
+    pub version: String,
+    /// Seconds since the Unix epoch.
+    pub started: u64,
+    pub finished: u64,
+    pub method: String,
+    /// One description per pass, as shown to the operator.
+    pub passes: Vec<String>,
+    pub rounds: u32,
+    /// Nothing was written: every job ran with a no-op write sink.
+    #[serde(default)]
+    pub dry_run: bool,
+    pub devices: Vec<DeviceReport>,
+}
+
+/// The result for one device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceReport {
+    pub target: PathBuf,
+    /// Identity of the drive at the start of the run; absent for image files.
+    pub drive: Option<Drive>,
+    /// `wiped`, `completed-with-unwritable-sectors`, `verify-failed`,
+    /// `io-error`, `vanished`, `identity-changed`, `interrupted` or
+    /// `refused`; `dry-run` for a dry run that went through every pass.
+    pub status: String,
+    pub error: Option<String>,
+    pub size: Option<u64>,
+    pub logical_sector_size: Option<u32>,
+    pub physical_sector_size: Option<u32>,
+    pub passes_written: u32,
+    pub bytes_written: u64,
+    pub bytes_verified: u64,
+    pub verified: bool,
+    /// Expected duration of the job, estimated before it started.
+    #[serde(default)]
+    pub estimated_seconds: u64,
+    /// Set when the wipe was interrupted and resumed from its journal.
+    #[serde(default)]
+    pub resumed: Option<Resumed>,
+    /// Logical sectors that could not be written, as end-exclusive LBA
+    /// ranges.
+    #[serde(default)]
+    pub bad_sectors: Vec<Range<u64>>,
+    /// The sample read back, when verifying by sampling: its seed, coverage
+    /// and the byte ranges checked.
+    #[serde(default)]
+    pub sample: Option<Sample>,
+    /// Passes were written with `O_DIRECT` rather than through the page
+    /// cache.
+    #[serde(default)]
+    pub direct_io: bool,
+    /// Whether the data went through io_uring or blocking calls.
+    #[serde(default)]
+    pub io_backend: IoBackend,
+}
+
+/// Current time as seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl DeviceReport {
+    pub fn new(
+        target: impl Into<PathBuf>,
+        drive: Option<Drive>,
+        status: &JobStatus,
+    ) -> DeviceReport {
+        let mut report = DeviceReport {
+            target: target.into(),
+            drive,
+            status: String::new(),
+            error: None,
+            size: None,
+            logical_sector_size: None,
+            physical_sector_size: None,
+            passes_written: 0,
+            bytes_written: 0,
+            bytes_verified: 0,
+            verified: false,
+            estimated_seconds: 0,
+            resumed: None,
+            bad_sectors: Vec::new(),
+            sample: None,
+            direct_io: false,
+            io_backend: IoBackend::Sync,
+        };
+        let (status, error) = match status {
+            JobStatus::Wiped(outcome) | JobStatus::CompletedWithBadSectors(outcome) => {
+                report.size = Some(outcome.geometry.size);
+                report.logical_sector_size = Some(outcome.geometry.logical_sector_size);
+                report.physical_sector_size = Some(outcome.geometry.physical_sector_size);
+                report.passes_written = outcome.passes;
+                report.bytes_written = outcome.bytes_written;
+                report.bytes_verified = outcome.bytes_verified;
+                report.verified = outcome.verification == Verification::Passed;
+                report.resumed = outcome.resumed;
+                report.bad_sectors = outcome.bad_sectors.clone();
+                report.sample = outcome.sample.clone();
+                report.direct_io = outcome.direct_io;
+                report.io_backend = outcome.io_backend;
+                if outcome.dry_run {
+                    ("dry-run", None)
+                } else if outcome.bad_sectors.is_empty() {
+                    ("wiped", None)
+                } else {
+                    ("completed-with-unwritable-sectors", None)
+                }
+            }
+            JobStatus::VerifyFailed(e) => ("verify-failed", Some(e)),
+            JobStatus::IoError(e) => ("io-error", Some(e)),
+            JobStatus::Vanished(e) => ("vanished", Some(e)),
+            JobStatus::IdentityChanged(e) => ("identity-changed", Some(e)),
+            JobStatus::Interrupted(e) => {
+                if let Error::Interrupted { pass, .. } = e {
+                    report.passes_written = pass.saturating_sub(1);
+                }
+                ("interrupted", Some(e))
+            }
+            JobStatus::Refused(e) => ("refused", Some(e)),
+        };
+        report.status = status.to_string();
+        report.error = error.map(ToString::to_string);
+        report
+    }
+}
+
+impl Report {
+    /// Starts a report for a run of `method`, timestamped now.
+    pub fn new(method: &WipeMethod, rounds: u32) -> Report {
+        let started = now();
+        Report {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started,
+            finished: started,
+            method: method.name.clone(),
+            passes: method.passes.iter().map(ToString::to_string).collect(),
+            rounds,
+            dry_run: false,
+            devices: Vec::new(),
+        }
+    }
+
+    /// Stamps the finish time.
+    pub fn finish(&mut self) {
+        self.finished = now();
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self).expect("reports always serialize");
+        fs::write(path, json + "\n").map_err(|e| Error::io(path, "write", e))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Report> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| Error::io(path, "read", e))?;
+        serde_json::from_str(&json).map_err(|e| {
+            Error::io(
+                path,
+                "parse",
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            )
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use crate::error::{Error, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::fmt;
+
+/// Key from which every random pass of a run derives its ChaCha20 stream.
+///
+/// Knowing the secret is enough to regenerate and verify every random pass,
+/// so it is only kept in memory, and on disk only when explicitly exported.
+/// Its `Debug` output is redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RunSecret([u8; 32]);
+
+impl RunSecret {
+    /// Draws a fresh secret from the operating system RNG.
+    pub fn generate() -> RunSecret {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        RunSecret(key)
+    }
+
+    pub fn from_bytes(key: [u8; 32]) -> RunSecret {
+        RunSecret(key)
+    }
+
+    /// Parses a secret from 64 hexadecimal digits.
+    pub fn from_hex(hex: &str) -> Result<RunSecret> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(Error::InvalidJob(
+                "a run secret must be 64 hexadecimal digits".into(),
+            ));
+        }
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| {
+                Error::InvalidJob("a run secret must be 64 hexadecimal digits".into())
+            })?;
+        }
+        Ok(RunSecret(key))
+    }
+
+    /// Hex encoding of the secret, for callers that explicitly export it.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Identifies the secret without revealing it or any pass's data: the
+    /// start of a stream no pass uses, in hex.
+    pub(crate) fn fingerprint(&self) -> String {
+        let mut rng = ChaCha20Rng::from_seed(self.0);
+        rng.set_stream(u64::MAX);
+        let mut id = [0u8; 8];
+        rng.fill_bytes(&mut id);
+        id.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// The keystream for pass number `pass`, positioned at byte 0.
+    pub(crate) fn stream(&self, pass: u32) -> ChaCha20Rng {
+        let mut rng = ChaCha20Rng::from_seed(self.0);
+        rng.set_stream(u64::from(pass));
+        rng
+    }
+}
+
+impl fmt::Debug for RunSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RunSecret(..)")
+    }
+}
+
+/// Fills `buf` with the keystream bytes found at byte `offset` of `rng`'s stream.
+pub(crate) fn fill_at(rng: &mut ChaCha20Rng, offset: u64, buf: &mut [u8]) {
+    let word = offset / 4;
+    let skip = (offset % 4) as usize;
+    rng.set_word_pos(u128::from(word));
+    if skip == 0 {
+        rng.fill_bytes(buf);
+        return;
+    }
+    let mut head = [0u8; 4];
+    rng.fill_bytes(&mut head);
+    let n = (4 - skip).min(buf.len());
+    buf[..n].copy_from_slice(&head[skip..skip + n]);
+    if buf.len() > n {
+        rng.set_word_pos(u128::from(word + 1));
+        rng.fill_bytes(&mut buf[n..]);
+    }
+}
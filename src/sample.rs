@@ -0,0 +1,130 @@
+//! Verification by sampling, as NIST SP 800-88 allows: pseudo-randomly
+//! chosen sections of the media plus the first and last ones.
+
+use crate::error::{Error, Result};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// Size of the sections a sample is drawn from.
+const SECTION: u64 = 1024 * 1024;
+
+/// Which share of a target to read back, and the seed that picks the
+/// sections, so that the same sample can be drawn again later.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sampling {
+    percent: f64,
+    seed: u64,
+}
+
+// The percentage is checked to be a finite number when constructed.
+impl Eq for Sampling {}
+
+impl Sampling {
+    /// Samples `percent` of the target, above 0 and at most 100, with the
+    /// sections picked by `seed`.
+    pub fn new(percent: f64, seed: u64) -> Result<Sampling> {
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(Error::InvalidJob(format!(
+                "the sample must be more than 0% and at most 100% of the target, not {}%",
+                percent
+            )));
+        }
+        Ok(Sampling { percent, seed })
+    }
+
+    /// Samples `percent` of the target with a seed drawn from the operating
+    /// system RNG.
+    pub fn random(percent: f64) -> Result<Sampling> {
+        Sampling::new(percent, OsRng.next_u64())
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.percent
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The sections of `regions` to read: the first and the last, plus
+    /// enough others picked by the seed to make up the percentage. Returns
+    /// sorted byte ranges, adjacent sections merged.
+    pub(crate) fn regions(&self, regions: &[Range<u64>]) -> Vec<Range<u64>> {
+        let counts: Vec<u64> = regions
+            .iter()
+            .map(|r| (r.end - r.start).div_ceil(SECTION))
+            .collect();
+        let sections: u64 = counts.iter().sum();
+        if sections == 0 {
+            return Vec::new();
+        }
+        let wanted = ((sections as f64 * self.percent / 100.0).ceil() as u64)
+            .clamp(sections.min(2), sections);
+
+        // Floyd's algorithm picks distinct sections in between, so the
+        // choice depends only on the seed and the number of sections.
+        let mut rng = ChaCha20Rng::seed_from_u64(self.seed);
+        let mut picked = BTreeSet::from([0, sections - 1]);
+        let inner = sections.saturating_sub(2);
+        for j in inner - (wanted - picked.len() as u64)..inner {
+            let candidate = 1 + rng.gen_range(0..=j);
+            if !picked.insert(candidate) {
+                picked.insert(1 + j);
+            }
+        }
+
+        let mut ranges: Vec<Range<u64>> = Vec::new();
+        let mut region = 0;
+        let mut first = 0;
+        for index in picked {
+            while index >= first + counts[region] {
+                first += counts[region];
+                region += 1;
+            }
+            let start = regions[region].start + (index - first) * SECTION;
+            let end = (start + SECTION).min(regions[region].end);
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+}
+
+/// The sample a verification read back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    pub sampling: Sampling,
+    /// Bytes read back in each verification.
+    pub bytes: u64,
+    /// Bytes each pass covers, of which `bytes` were sampled.
+    pub total: u64,
+    /// Byte ranges read back.
+    pub regions: Vec<Range<u64>>,
+}
+
+impl Sample {
+    pub(crate) fn new(sampling: Sampling, regions: &[Range<u64>]) -> Sample {
+        let sampled = sampling.regions(regions);
+        Sample {
+            sampling,
+            bytes: sampled.iter().map(|r| r.end - r.start).sum(),
+            total: regions.iter().map(|r| r.end - r.start).sum(),
+            regions: sampled,
+        }
+    }
+
+    /// Share of the target read back, in percent.
+    pub fn coverage(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+        self.bytes as f64 * 100.0 / self.total as f64
+    }
+}
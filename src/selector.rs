@@ -0,0 +1,141 @@
+//! Target selectors such as `serial:WD-XXXX`, resolved against the drive
+//! inventory instead of naming a `/dev` node directly.
+
+use crate::discovery::{Drive, Transport};
+use crate::error::{Error, Result};
+use crate::policy::glob_match;
+use crate::sysfs::SysRoot;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// One way of naming wipe targets on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// A device node or image file, used as given.
+    Path(PathBuf),
+    /// `serial:<serial>`, matched exactly.
+    Serial(String),
+    /// `wwn:<wwn>`, with or without the `0x` prefix, in any case.
+    Wwn(String),
+    /// `by-id:<link>`, a `/dev/disk/by-id` link name or full path.
+    ById(String),
+    /// `model:<glob>`, selecting every drive whose model matches.
+    Model(String),
+    /// `transport:<bus>`, selecting every drive on that bus.
+    Transport(Transport),
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Selector> {
+        let Some((kind, value)) = s.split_once(':') else {
+            return Ok(Selector::Path(PathBuf::from(s)));
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        let known = ["serial", "wwn", "by-id", "model", "transport"];
+        if known.contains(&kind) && value.is_empty() {
+            return Err(Error::InvalidSelector(format!("{} needs a value", s)));
+        }
+        let value = value.to_string();
+        Ok(match kind {
+            "serial" => Selector::Serial(value),
+            "wwn" => Selector::Wwn(value),
+            "by-id" => Selector::ById(value),
+            "model" => Selector::Model(value),
+            "transport" => {
+                Selector::Transport(value.parse().map_err(|_| {
+                    Error::InvalidSelector(format!("unknown transport '{}'", value))
+                })?)
+            }
+            _ => Selector::Path(PathBuf::from(s)),
+        })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Path(path) => write!(f, "{}", path.display()),
+            Selector::Serial(serial) => write!(f, "serial:{}", serial),
+            Selector::Wwn(wwn) => write!(f, "wwn:{}", wwn),
+            Selector::ById(link) => write!(f, "by-id:{}", link),
+            Selector::Model(glob) => write!(f, "model:\"{}\"", glob),
+            Selector::Transport(transport) => write!(f, "transport:{}", transport),
+        }
+    }
+}
+
+/// A WWN without its `0x` prefix, in lowercase.
+fn bare_wwn(wwn: &str) -> String {
+    let wwn = wwn.to_ascii_lowercase();
+    wwn.strip_prefix("0x").unwrap_or(&wwn).to_string()
+}
+
+impl Selector {
+    /// True for selectors that name a single drive, where matching more
+    /// than one is always an error.
+    pub fn is_unique(&self) -> bool {
+        matches!(
+            self,
+            Selector::Path(_) | Selector::Serial(_) | Selector::Wwn(_) | Selector::ById(_)
+        )
+    }
+
+    fn matches(&self, drive: &Drive) -> bool {
+        match self {
+            Selector::Path(path) => drive.path == *path,
+            Selector::Serial(serial) => drive.serial.as_deref() == Some(serial.as_str()),
+            Selector::Wwn(wwn) => drive.wwn.as_deref().map(bare_wwn) == Some(bare_wwn(wwn)),
+            Selector::ById(link) => {
+                let name = Path::new(link).file_name();
+                drive
+                    .by_id
+                    .iter()
+                    .any(|path| name.is_some() && path.file_name() == name)
+            }
+            Selector::Model(glob) => drive
+                .model
+                .as_deref()
+                .is_some_and(|model| glob_match(glob, model)),
+            Selector::Transport(transport) => drive.transport == *transport,
+        }
+    }
+}
+
+impl SysRoot {
+    /// The drives `selector` names. Paths resolve to the drive behind them,
+    /// or to nothing for image files. Every other selector must match at
+    /// least one drive, and exactly one unless `all_matching` is set; those
+    /// naming a single drive must match exactly one regardless.
+    pub fn select(&self, selector: &Selector, all_matching: bool) -> Result<Vec<Drive>> {
+        if let Selector::Path(path) = selector {
+            return Ok(self.drive_for(path)?.into_iter().collect());
+        }
+        let drives: Vec<Drive> = self
+            .drives()?
+            .into_iter()
+            .filter(|drive| selector.matches(drive))
+            .collect();
+        match drives.len() {
+            0 => Err(Error::InvalidSelector(format!(
+                "{} matches no drive",
+                selector
+            ))),
+            1 => Ok(drives),
+            _ if all_matching && !selector.is_unique() => Ok(drives),
+            _ => {
+                let names: Vec<_> = drives.iter().map(|d| d.name.as_str()).collect();
+                Err(Error::InvalidSelector(format!(
+                    "{} is ambiguous: it matches {}",
+                    selector,
+                    names.join(", ")
+                )))
+            }
+        }
+    }
+}
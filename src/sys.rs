@@ -0,0 +1,158 @@
+//! Thin wrappers around the Linux block device ioctls and file syscalls.
+
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::Range;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Logical sector size in bytes (`BLKSSZGET`).
+pub(crate) fn logical_sector_size(file: &File) -> io::Result<u32> {
+    let mut size: libc::c_int = 0;
+    // SAFETY: BLKSSZGET writes a single int through the pointer.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::BLKSSZGET, &mut size) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(size as u32)
+}
+
+/// Physical sector size in bytes (`BLKPBSZGET`).
+pub(crate) fn physical_sector_size(file: &File) -> io::Result<u32> {
+    let mut size: libc::c_uint = 0;
+    // SAFETY: BLKPBSZGET writes a single unsigned int through the pointer.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::BLKPBSZGET, &mut size) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(size)
+}
+
+/// `_IOR(0x12, 114, size_t)`, which `libc` does not export.
+const BLKGETSIZE64: libc::Ioctl =
+    ((2 << 30) | (std::mem::size_of::<usize>() << 16) | (0x12 << 8) | 114) as libc::Ioctl;
+
+/// Size of a block device in bytes (`BLKGETSIZE64`).
+pub(crate) fn device_size(file: &File) -> io::Result<u64> {
+    let mut size: u64 = 0;
+    // SAFETY: BLKGETSIZE64 writes a single u64 through the pointer.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), BLKGETSIZE64, &mut size) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(size)
+}
+
+/// Unmounts the filesystem mounted at `target` with `umount2(2)`.
+pub(crate) fn unmount(target: &Path) -> io::Result<()> {
+    let target = CString::new(target.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+    // SAFETY: `target` is a valid NUL-terminated string for the duration of the call.
+    let ret = unsafe { libc::umount2(target.as_ptr(), 0) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Byte ranges of `file` below `size` that hold data, found with
+/// `lseek(SEEK_DATA)` and `lseek(SEEK_HOLE)`.
+pub(crate) fn data_extents(file: &File, size: u64) -> io::Result<Vec<Range<u64>>> {
+    let fd = file.as_raw_fd();
+    let mut extents = Vec::new();
+    let mut offset = 0;
+    while offset < size {
+        // SAFETY: lseek only moves the descriptor's offset, which positional
+        // reads and writes do not use.
+        let start = unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_DATA) };
+        if start < 0 {
+            let err = io::Error::last_os_error();
+            // ENXIO means there is no data past `offset`.
+            if err.raw_os_error() == Some(libc::ENXIO) {
+                break;
+            }
+            return Err(err);
+        }
+        // SAFETY: as above.
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (start, end) = (start as u64, (end as u64).min(size));
+        if start >= end {
+            break;
+        }
+        extents.push(start..end);
+        offset = end;
+    }
+    Ok(extents)
+}
+
+/// Deallocates `range` of `file` without changing its size.
+pub(crate) fn punch_hole(file: &File, range: Range<u64>) -> io::Result<()> {
+    // SAFETY: fallocate only operates on the descriptor.
+    let ret = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+            range.start as libc::off_t,
+            (range.end - range.start) as libc::off_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// `_IO(0x12, 119)`, which `libc` does not export.
+const BLKDISCARD: libc::Ioctl = ((0x12 << 8) | 119) as libc::Ioctl;
+
+/// Discards `range` of a block device (`BLKDISCARD`).
+pub(crate) fn discard(file: &File, range: Range<u64>) -> io::Result<()> {
+    let args: [u64; 2] = [range.start, range.end - range.start];
+    // SAFETY: BLKDISCARD reads two u64s, offset and length, from the pointer.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), BLKDISCARD, &args) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// `_IO(0x12, 97)`, which `libc` does not export.
+const BLKFLSBUF: libc::Ioctl = ((0x12 << 8) | 97) as libc::Ioctl;
+
+/// Writes back and invalidates the buffer cache of a block device
+/// (`BLKFLSBUF`).
+pub(crate) fn flush_buffers(file: &File) -> io::Result<()> {
+    // SAFETY: BLKFLSBUF takes no argument.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), BLKFLSBUF, 0) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Drops the clean cached pages of a regular file
+/// (`posix_fadvise(POSIX_FADV_DONTNEED)`).
+pub(crate) fn drop_cache(file: &File) -> io::Result<()> {
+    // SAFETY: posix_fadvise only operates on the descriptor.
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret));
+    }
+    Ok(())
+}
+
+/// Opens the file behind `file` again with `O_DIRECT`, through
+/// `/proc/self/fd`, so that I/O bypasses the page cache.
+pub(crate) fn reopen_direct(file: &File, write: bool) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(write)
+        .custom_flags(libc::O_DIRECT)
+        .open(format!("/proc/self/fd/{}", file.as_raw_fd()))
+}
@@ -0,0 +1,222 @@
+//! Access to `/sys` and `/proc`, relative to a configurable root.
+
+use crate::error::{Error, Result};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A block device number, serialized as `"major:minor"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DevId {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl DevId {
+    /// Splits a raw `dev_t` using the glibc encoding.
+    pub fn from_raw(dev: u64) -> DevId {
+        DevId {
+            major: (((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0xfff)) as u32,
+            minor: (((dev >> 12) & 0xffff_ff00) | (dev & 0xff)) as u32,
+        }
+    }
+
+    /// The device number of the block device node at `path`.
+    pub fn of(path: impl AsRef<Path>) -> Result<DevId> {
+        let path = path.as_ref();
+        let metadata = fs::metadata(path).map_err(|e| Error::io(path, "stat", e))?;
+        if !metadata.file_type().is_block_device() {
+            return Err(Error::InvalidJob(format!(
+                "{} is not a block device",
+                path.display()
+            )));
+        }
+        Ok(DevId::from_raw(metadata.rdev()))
+    }
+}
+
+impl fmt::Display for DevId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for DevId {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<DevId, ()> {
+        let (major, minor) = s.trim().split_once(':').ok_or(())?;
+        Ok(DevId {
+            major: major.parse().map_err(|_| ())?,
+            minor: minor.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+impl Serialize for DevId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DevId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|()| de::Error::custom(format!("invalid device number '{}'", s)))
+    }
+}
+
+/// A block device known to sysfs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDev {
+    /// Kernel name, such as `sda1` or `dm-0`.
+    pub name: String,
+    pub dev: DevId,
+}
+
+/// The root under which `proc/` and `sys/` are found; `/` on a live system.
+#[derive(Debug, Clone)]
+pub struct SysRoot {
+    root: PathBuf,
+}
+
+impl Default for SysRoot {
+    fn default() -> Self {
+        SysRoot::new("/")
+    }
+}
+
+impl SysRoot {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        SysRoot { root: root.into() }
+    }
+
+    pub fn proc_path(&self, rel: &str) -> PathBuf {
+        self.root.join("proc").join(rel)
+    }
+
+    pub fn sys_path(&self, rel: &str) -> PathBuf {
+        self.root.join("sys").join(rel)
+    }
+
+    pub fn dev_path(&self, rel: &str) -> PathBuf {
+        self.root.join("dev").join(rel)
+    }
+
+    /// Reads a sysfs attribute, trimmed. Missing attributes read as `None`.
+    pub(crate) fn attr(&self, path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+    }
+
+    /// The sysfs directory of the block device `dev`.
+    pub(crate) fn block_dir(&self, dev: DevId) -> PathBuf {
+        self.sys_path("dev/block").join(dev.to_string())
+    }
+
+    /// The kernel name of `dev`, from its `uevent`.
+    pub fn name_of(&self, dev: DevId) -> Result<String> {
+        let uevent = self.block_dir(dev).join("uevent");
+        let contents = fs::read_to_string(&uevent).map_err(|e| Error::io(&uevent, "read", e))?;
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVNAME="))
+            .map(str::to_string)
+            .ok_or_else(|| Error::InvalidJob(format!("{} has no DEVNAME", uevent.display())))
+    }
+
+    /// Looks up a block device by kernel name, such as `sda1`.
+    pub fn by_name(&self, name: &str) -> Option<BlockDev> {
+        let dev = self
+            .attr(&self.sys_path("class/block").join(name).join("dev"))?
+            .parse()
+            .ok()?;
+        Some(BlockDev {
+            name: name.to_string(),
+            dev,
+        })
+    }
+
+    /// Resolves a `/dev` path (including `/dev/disk/by-*` and `/dev/mapper`
+    /// links) to the block device it names.
+    pub fn by_path(&self, path: &Path) -> Option<BlockDev> {
+        let resolved = fs::canonicalize(path)
+            .or_else(|_| fs::read_link(path))
+            .unwrap_or_else(|_| path.to_path_buf());
+        self.by_name(resolved.file_name()?.to_str()?)
+    }
+
+    /// The partitions of the whole disk `dev`, in sysfs order.
+    pub fn partitions(&self, dev: DevId) -> Result<Vec<BlockDev>> {
+        let dir = self.block_dir(dev);
+        let entries = fs::read_dir(&dir).map_err(|e| Error::io(&dir, "read", e))?;
+        let mut partitions: Vec<BlockDev> = entries
+            .flatten()
+            .filter(|entry| entry.path().join("partition").exists())
+            .filter_map(|entry| {
+                let dev = self.attr(&entry.path().join("dev"))?.parse().ok()?;
+                Some(BlockDev {
+                    name: entry.file_name().to_str()?.to_string(),
+                    dev,
+                })
+            })
+            .collect();
+        partitions.sort_by_key(|p| p.dev);
+        Ok(partitions)
+    }
+
+    /// Devices stacked directly on `dev`, such as device-mapper or md arrays.
+    pub fn holders(&self, dev: DevId) -> Vec<BlockDev> {
+        let dir = self.block_dir(dev).join("holders");
+        let mut holders: Vec<BlockDev> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|entry| self.by_name(entry.file_name().to_str()?))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        holders.sort_by_key(|h| h.dev);
+        holders
+    }
+
+    /// The devices `dev` is stacked on, such as the members of a dm or md device.
+    pub fn slaves(&self, dev: DevId) -> Vec<BlockDev> {
+        let dir = self.block_dir(dev).join("slaves");
+        match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|entry| self.by_name(entry.file_name().to_str()?))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Links in `dev/disk/by-id` that point at the device named `name`.
+    pub fn by_id_links(&self, name: &str) -> Vec<PathBuf> {
+        let dir = self.dev_path("disk/by-id");
+        let mut links: Vec<PathBuf> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|entry| {
+                    fs::read_link(entry.path())
+                        .is_ok_and(|target| target.file_name().is_some_and(|f| f == name))
+                })
+                .map(|entry| Path::new("/dev/disk/by-id").join(entry.file_name()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        links.sort();
+        links
+    }
+
+    /// A human-readable label for `dev`, including the device-mapper name.
+    pub fn display_name(&self, dev: &BlockDev) -> String {
+        match self.attr(&self.sys_path("class/block").join(&dev.name).join("dm/name")) {
+            Some(dm_name) => format!("{} ({})", dev.name, dm_name),
+            None => dev.name.clone(),
+        }
+    }
+}
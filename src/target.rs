@@ -0,0 +1,565 @@
+//! The storage a job reads and writes, behind one trait so that the pass
+//! engine runs the same way against block devices, image files, memory, or
+//! a wrapper that injects faults.
+
+use crate::buffer::{AlignedBuf, ALIGN};
+use crate::device::{self, Geometry};
+use crate::error::{Error, Result};
+use crate::sys;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::os::unix::fs::{FileExt, FileTypeExt};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Random-access storage that a wipe can be run against.
+pub trait BlockTarget: Send + Sync {
+    fn geometry(&self) -> Geometry;
+
+    fn size(&self) -> u64 {
+        self.geometry().size
+    }
+
+    /// Logical sector size in bytes.
+    fn sector_size(&self) -> u32 {
+        self.geometry().logical_sector_size
+    }
+
+    /// Writes up to `buf.len()` bytes at `offset`, returning how many were
+    /// written.
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize>;
+
+    /// Reads up to `buf.len()` bytes at `offset`, returning how many were
+    /// read; 0 at the end of the target.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Makes everything written so far durable.
+    fn flush(&self) -> io::Result<()>;
+
+    /// Tells the storage that `range` no longer holds data.
+    fn discard(&self, range: Range<u64>) -> io::Result<()>;
+
+    /// Writes back everything the kernel caches for the target and drops it
+    /// from the cache, so that the next reads come from the storage.
+    fn invalidate_cache(&self) -> io::Result<()> {
+        self.flush()
+    }
+
+    /// Another handle on the same storage whose reads bypass the page cache
+    /// (`O_DIRECT`); `None` where there is no cache to bypass or the storage
+    /// does not support direct I/O.
+    fn direct_reader(&self) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        Ok(None)
+    }
+
+    /// Like [`direct_reader`](BlockTarget::direct_reader), for writing as
+    /// well. Writes that are not sector-aligned, in memory, offset or
+    /// length, may still go through the page cache.
+    fn direct_writer(&self) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        Ok(None)
+    }
+
+    /// The descriptor reads and writes go to, for backends that submit I/O
+    /// to the kernel themselves, such as io_uring; `None` for targets that
+    /// are not a plain file, which always use the blocking calls above.
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
+
+    /// The ranges holding data, for targets that can be sparse; `None` if
+    /// every byte is backed.
+    fn data_extents(&self) -> io::Result<Option<Vec<Range<u64>>>> {
+        Ok(None)
+    }
+
+    /// Writes all of `buf` at `offset`, continuing after short writes.
+    fn write_all_at(&self, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write_at(buf, offset) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills all of `buf` from `offset`, continuing after short reads.
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A block device, such as `/dev/sdb`.
+#[derive(Debug)]
+pub struct BlockDevice {
+    file: File,
+    geometry: Geometry,
+}
+
+impl BlockDevice {
+    /// Wraps an open block device, querying its geometry.
+    pub fn new(file: File, path: &Path) -> Result<BlockDevice> {
+        let geometry = device::geometry(&file, path)?;
+        Ok(BlockDevice { file, geometry })
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+}
+
+impl BlockTarget for BlockDevice {
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.file.write_at(buf, offset)
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.file.read_at(buf, offset)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    fn discard(&self, range: Range<u64>) -> io::Result<()> {
+        sys::discard(&self.file, range)
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.file.as_raw_fd())
+    }
+
+    /// Syncs the device, then invalidates its buffer cache (`BLKFLSBUF`).
+    fn invalidate_cache(&self) -> io::Result<()> {
+        self.file.sync_data()?;
+        sys::flush_buffers(&self.file)
+    }
+
+    fn direct_reader(&self) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        DirectFile::open(
+            &self.file,
+            self.geometry,
+            self.geometry.logical_sector_size,
+            false,
+        )
+    }
+
+    fn direct_writer(&self) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        DirectFile::open(
+            &self.file,
+            self.geometry,
+            self.geometry.logical_sector_size,
+            true,
+        )
+    }
+}
+
+/// A regular file, such as a raw disk image.
+#[derive(Debug)]
+pub struct RegularFile {
+    file: File,
+    geometry: Geometry,
+}
+
+impl RegularFile {
+    /// Wraps an open regular file, sized from its metadata.
+    pub fn new(file: File, path: &Path) -> Result<RegularFile> {
+        let geometry = device::geometry(&file, path)?;
+        Ok(RegularFile { file, geometry })
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+}
+
+impl BlockTarget for RegularFile {
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.file.write_at(buf, offset)
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.file.read_at(buf, offset)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    /// Punches a hole, keeping the file size.
+    fn discard(&self, range: Range<u64>) -> io::Result<()> {
+        sys::punch_hole(&self.file, range)
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.file.as_raw_fd())
+    }
+
+    fn data_extents(&self) -> io::Result<Option<Vec<Range<u64>>>> {
+        sys::data_extents(&self.file, self.geometry.size).map(Some)
+    }
+
+    /// Syncs the file, then drops its pages from the page cache.
+    fn invalidate_cache(&self) -> io::Result<()> {
+        self.file.sync_data()?;
+        sys::drop_cache(&self.file)
+    }
+
+    /// Aligns direct I/O to the filesystem block size. Filesystems that
+    /// reject `O_DIRECT`, such as tmpfs, get no direct reader.
+    fn direct_reader(&self) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        DirectFile::open(
+            &self.file,
+            self.geometry,
+            self.geometry.physical_sector_size,
+            false,
+        )
+    }
+
+    fn direct_writer(&self) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        DirectFile::open(
+            &self.file,
+            self.geometry,
+            self.geometry.physical_sector_size,
+            true,
+        )
+    }
+}
+
+/// A handle opened with `O_DIRECT`, next to the buffered one it was opened
+/// from. Reads that are not aligned to the sector size, in memory, offset
+/// or length, go through an aligned bounce buffer; such writes, and writes
+/// the storage refuses to take directly, go through the buffered handle.
+struct DirectFile {
+    direct: File,
+    buffered: File,
+    geometry: Geometry,
+    align: u64,
+    writable: bool,
+}
+
+impl DirectFile {
+    fn open(
+        file: &File,
+        geometry: Geometry,
+        align: u32,
+        writable: bool,
+    ) -> io::Result<Option<Box<dyn BlockTarget>>> {
+        match sys::reopen_direct(file, writable) {
+            Ok(direct) => Ok(Some(Box::new(DirectFile {
+                direct,
+                buffered: file.try_clone()?,
+                geometry,
+                align: u64::from(align.max(512)),
+                writable,
+            }))),
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// True if an operation on `buf` at `offset` can go straight through
+    /// the direct handle.
+    fn is_aligned(&self, buf: &[u8], offset: u64) -> bool {
+        let address = buf.as_ptr() as u64;
+        address.is_multiple_of(self.align.min(ALIGN as u64))
+            && offset.is_multiple_of(self.align)
+            && (buf.len() as u64).is_multiple_of(self.align)
+    }
+}
+
+impl BlockTarget for DirectFile {
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        if !self.writable {
+            return Err(io::Error::from_raw_os_error(libc::EBADF));
+        }
+        if self.is_aligned(buf, offset) {
+            match self.direct.write_at(buf, offset) {
+                Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {}
+                result => return result,
+            }
+        }
+        self.buffered.write_at(buf, offset)
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if self.is_aligned(buf, offset) {
+            return self.direct.read_at(buf, offset);
+        }
+        let start = offset / self.align * self.align;
+        let end = (offset + buf.len() as u64).div_ceil(self.align) * self.align;
+        let mut bounce = AlignedBuf::new((end - start) as usize);
+        let read = self.direct.read_at(&mut bounce, start)?;
+        let skip = (offset - start) as usize;
+        let n = read.saturating_sub(skip).min(buf.len());
+        buf[..n].copy_from_slice(&bounce[skip..skip + n]);
+        Ok(n)
+    }
+
+    /// Syncs both handles' writes; they share one inode.
+    fn flush(&self) -> io::Result<()> {
+        self.buffered.sync_data()
+    }
+
+    fn discard(&self, _range: Range<u64>) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// The direct handle. Operations it rejects as unaligned are redone
+    /// through [`write_at`](BlockTarget::write_at) and
+    /// [`read_at`](BlockTarget::read_at).
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.direct.as_raw_fd())
+    }
+}
+
+/// Wraps an open block device or regular file in the matching target.
+pub(crate) fn open_target(file: &File, path: &Path) -> Result<Box<dyn BlockTarget>> {
+    let metadata = file.metadata().map_err(|e| Error::io(path, "stat", e))?;
+    let file = file.try_clone().map_err(|e| Error::io(path, "open", e))?;
+    if metadata.file_type().is_block_device() {
+        Ok(Box::new(BlockDevice::new(file, path)?))
+    } else {
+        Ok(Box::new(RegularFile::new(file, path)?))
+    }
+}
+
+/// A device held in memory, for tests and simulations.
+#[derive(Debug)]
+pub struct MemoryTarget {
+    data: Mutex<Vec<u8>>,
+    geometry: Geometry,
+}
+
+impl MemoryTarget {
+    /// A zeroed device of `size` bytes with 512-byte sectors.
+    pub fn new(size: u64) -> MemoryTarget {
+        MemoryTarget::with_sectors(size, 512, 512)
+    }
+
+    /// A zeroed device of `size` bytes with the given sector sizes.
+    pub fn with_sectors(size: u64, logical: u32, physical: u32) -> MemoryTarget {
+        MemoryTarget {
+            data: Mutex::new(vec![0; size as usize]),
+            geometry: Geometry {
+                size,
+                logical_sector_size: logical,
+                physical_sector_size: physical,
+            },
+        }
+    }
+
+    /// A copy of the current contents.
+    pub fn contents(&self) -> Vec<u8> {
+        self.data.lock().unwrap().clone()
+    }
+
+    /// Overwrites part of the contents directly, bypassing any wrapper.
+    pub fn poke(&self, offset: u64, bytes: &[u8]) {
+        let offset = offset as usize;
+        self.data.lock().unwrap()[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+impl BlockTarget for MemoryTarget {
+    fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let start = offset.min(data.len() as u64) as usize;
+        let len = buf.len().min(data.len() - start);
+        if len == 0 && !buf.is_empty() {
+            return Err(io::Error::from_raw_os_error(libc::ENOSPC));
+        }
+        data[start..start + len].copy_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let data = self.data.lock().unwrap();
+        let start = offset.min(data.len() as u64) as usize;
+        let len = buf.len().min(data.len() - start);
+        buf[..len].copy_from_slice(&data[start..start + len]);
+        Ok(len)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Discarded ranges read back as zeros.
+    fn discard(&self, range: Range<u64>) -> io::Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let end = (range.end as usize).min(data.len());
+        let start = (range.start as usize).min(end);
+        data[start..end].fill(0);
+        Ok(())
+    }
+}
+
+/// Wraps a target and makes it misbehave, to exercise error handling:
+///
+/// ```
+/// use std::time::Duration;
+/// use wipers::{FaultyTarget, MemoryTarget};
+///
+/// let target = FaultyTarget::new(MemoryTarget::new(1 << 20))
+///     .eio_at(100)
+///     .short_writes(4096)
+///     .latency_spike(64, Duration::from_millis(5))
+///     .disappear_after(512 * 1024);
+/// ```
+#[derive(Debug)]
+pub struct FaultyTarget<T> {
+    inner: T,
+    bad_sectors: Vec<u64>,
+    max_write: Option<usize>,
+    latency: Option<(u64, Duration)>,
+    disappear_after: Option<u64>,
+    ops: AtomicU64,
+    written: AtomicU64,
+    gone: AtomicBool,
+}
+
+impl<T: BlockTarget> FaultyTarget<T> {
+    pub fn new(inner: T) -> FaultyTarget<T> {
+        FaultyTarget {
+            inner,
+            bad_sectors: Vec::new(),
+            max_write: None,
+            latency: None,
+            disappear_after: None,
+            ops: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+            gone: AtomicBool::new(false),
+        }
+    }
+
+    /// Fails every read or write touching logical sector `lba` with `EIO`.
+    pub fn eio_at(mut self, lba: u64) -> Self {
+        self.bad_sectors.push(lba);
+        self
+    }
+
+    /// Writes at most `max` bytes per call.
+    pub fn short_writes(mut self, max: usize) -> Self {
+        self.max_write = Some(max.max(1));
+        self
+    }
+
+    /// Stalls every `every`th operation for `delay`.
+    pub fn latency_spike(mut self, every: u64, delay: Duration) -> Self {
+        self.latency = Some((every.max(1), delay));
+        self
+    }
+
+    /// Fails everything with `ENODEV` once `bytes` have been written, as if
+    /// the device had been unplugged.
+    pub fn disappear_after(mut self, bytes: u64) -> Self {
+        self.disappear_after = Some(bytes);
+        self
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Applies latency and disappearance, and checks `offset..offset + len`
+    /// for bad sectors, before an operation reaches the inner target.
+    fn before(&self, offset: u64, len: usize) -> io::Result<()> {
+        let op = self.ops.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some((every, delay)) = self.latency {
+            if op.is_multiple_of(every) {
+                thread::sleep(delay);
+            }
+        }
+        if self.gone.load(Ordering::Relaxed) {
+            return Err(io::Error::from_raw_os_error(libc::ENODEV));
+        }
+        let sector = u64::from(self.inner.sector_size().max(1));
+        let end = offset + len as u64;
+        if self
+            .bad_sectors
+            .iter()
+            .any(|lba| lba * sector < end && (lba + 1) * sector > offset)
+        {
+            return Err(io::Error::from_raw_os_error(libc::EIO));
+        }
+        Ok(())
+    }
+}
+
+impl<T: BlockTarget> BlockTarget for FaultyTarget<T> {
+    fn geometry(&self) -> Geometry {
+        self.inner.geometry()
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.before(offset, buf.len())?;
+        let len = self.max_write.map_or(buf.len(), |max| buf.len().min(max));
+        let n = self.inner.write_at(&buf[..len], offset)?;
+        let written = self.written.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+        if self.disappear_after.is_some_and(|limit| written >= limit) {
+            self.gone.store(true, Ordering::Relaxed);
+        }
+        Ok(n)
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.before(offset, buf.len())?;
+        self.inner.read_at(buf, offset)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.before(0, 0)?;
+        self.inner.flush()
+    }
+
+    fn discard(&self, range: Range<u64>) -> io::Result<()> {
+        self.before(range.start, (range.end - range.start) as usize)?;
+        self.inner.discard(range)
+    }
+
+    fn data_extents(&self) -> io::Result<Option<Vec<Range<u64>>>> {
+        self.inner.data_extents()
+    }
+}
@@ -0,0 +1,188 @@
+//! io_uring submission of a batch of chunk reads or writes, from buffers
+//! registered with the kernel once per pass.
+
+use crate::buffer::AlignedBuf;
+use io_uring::{opcode, types, IoUring};
+use std::io;
+use std::os::unix::io::RawFd;
+
+/// `io_uring_enter` flag to wait for completions; the crate keeps its own
+/// copy private.
+const IORING_ENTER_GETEVENTS: u32 = 1;
+
+/// True if the kernel lets this process set up an io_uring instance.
+pub(crate) fn available() -> bool {
+    IoUring::new(1).is_ok()
+}
+
+/// A ring for one descriptor, owning the buffers registered with it.
+pub(crate) struct Ring {
+    // Declared first so that the ring, and with it the registration, goes
+    // away before the buffers are freed.
+    ring: IoUring,
+    fd: RawFd,
+    buffers: Vec<AlignedBuf>,
+    /// Set once a submission fails; entries the kernel did not take may
+    /// still sit in the queue, so nothing more goes through the ring.
+    broken: bool,
+}
+
+impl Ring {
+    /// Sets up a ring for `fd` with `buffers` registered, or gives the
+    /// buffers back if io_uring is unavailable or refuses them.
+    pub(crate) fn new(fd: RawFd, buffers: Vec<AlignedBuf>) -> Result<Ring, Vec<AlignedBuf>> {
+        let Ok(ring) = IoUring::new(buffers.len().next_power_of_two() as u32) else {
+            return Err(buffers);
+        };
+        let iovecs: Vec<libc::iovec> = buffers
+            .iter()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        // SAFETY: the buffers are owned by the ring and dropped after it.
+        match unsafe { ring.submitter().register_buffers(&iovecs) } {
+            Ok(()) => Ok(Ring {
+                ring,
+                fd,
+                buffers,
+                broken: false,
+            }),
+            Err(_) => Err(buffers),
+        }
+    }
+
+    pub(crate) fn buffers(&self) -> &[AlignedBuf] {
+        &self.buffers
+    }
+
+    pub(crate) fn buffers_mut(&mut self) -> &mut [AlignedBuf] {
+        &mut self.buffers
+    }
+
+    /// Writes `len` bytes of buffer `i` at `offset` for every
+    /// `(i, offset, len)` in `ops`, all in flight at once. Returns each
+    /// write's result in the order of `ops`.
+    pub(crate) fn write(&mut self, ops: &[(usize, u64, usize)]) -> Vec<io::Result<usize>> {
+        let entries: Vec<_> = ops
+            .iter()
+            .map(|&(i, offset, len)| {
+                opcode::WriteFixed::new(
+                    types::Fd(self.fd),
+                    self.buffers[i].as_ptr(),
+                    len as u32,
+                    i as u16,
+                )
+                .offset(offset)
+                .build()
+            })
+            .collect();
+        self.run(entries)
+    }
+
+    /// Reads `len` bytes at `offset` into buffer `i` for every
+    /// `(i, offset, len)` in `ops`, all in flight at once. Returns each
+    /// read's result in the order of `ops`.
+    pub(crate) fn read(&mut self, ops: &[(usize, u64, usize)]) -> Vec<io::Result<usize>> {
+        let entries: Vec<_> = ops
+            .iter()
+            .map(|&(i, offset, len)| {
+                opcode::ReadFixed::new(
+                    types::Fd(self.fd),
+                    self.buffers[i].as_mut_ptr(),
+                    len as u32,
+                    i as u16,
+                )
+                .offset(offset)
+                .build()
+            })
+            .collect();
+        self.run(entries)
+    }
+
+    /// Submits `entries`, tagged with their index, and waits for all of
+    /// them to complete. Entries that never complete come back as errors.
+    fn run(&mut self, entries: Vec<io_uring::squeue::Entry>) -> Vec<io::Result<usize>> {
+        let count = entries.len();
+        let mut results: Vec<Option<io::Result<usize>>> = (0..count).map(|_| None).collect();
+        let mut failure = io::ErrorKind::Other;
+        let mut queued = 0;
+        if !self.broken {
+            for (n, entry) in entries.into_iter().enumerate() {
+                // SAFETY: every entry points into a registered buffer owned
+                // by `self`, which stays borrowed until the entry completes
+                // below.
+                if unsafe { self.ring.submission().push(&entry.user_data(n as u64)) }.is_err() {
+                    break;
+                }
+                queued += 1;
+            }
+        }
+
+        let mut reaped = 0;
+        while reaped < queued {
+            match self.ring.submit_and_wait(queued - reaped) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // The kernel may have taken only some of the entries.
+                    // Wait for those, so none is still using its buffer, and
+                    // stop using the ring: the rest would go in with the
+                    // next batch.
+                    failure = e.kind();
+                    self.broken = true;
+                    let submitted = queued - self.ring.submission().len();
+                    self.drain(submitted - reaped, &mut results);
+                    break;
+                }
+            }
+            reaped += self.reap(&mut results);
+        }
+        results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| {
+                    Err(io::Error::new(
+                        failure,
+                        "io_uring did not complete the operation",
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Records the result of every completion waiting on the ring, and
+    /// returns how many there were.
+    fn reap(&mut self, results: &mut [Option<io::Result<usize>>]) -> usize {
+        let mut reaped = 0;
+        for cqe in self.ring.completion() {
+            results[cqe.user_data() as usize] = Some(match cqe.result() {
+                r if r < 0 => Err(io::Error::from_raw_os_error(-r)),
+                r => Ok(r as usize),
+            });
+            reaped += 1;
+        }
+        reaped
+    }
+
+    /// Waits for the `in_flight` operations the kernel has already taken,
+    /// without submitting anything more, and records their results.
+    fn drain(&mut self, mut in_flight: usize, results: &mut [Option<io::Result<usize>>]) {
+        in_flight = in_flight.saturating_sub(self.reap(results));
+        while in_flight > 0 {
+            // SAFETY: submits nothing and passes no argument.
+            let waited = unsafe {
+                self.ring
+                    .submitter()
+                    .enter::<libc::sigset_t>(0, 1, IORING_ENTER_GETEVENTS, None)
+            };
+            match waited {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+            in_flight = in_flight.saturating_sub(self.reap(results));
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Detects mounts, swap and stacked devices that keep a disk busy.
+
+use crate::error::{Error, Result};
+use crate::sys;
+use crate::sysfs::{BlockDev, DevId, SysRoot};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One entry of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub dev: DevId,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+}
+
+/// Why a device cannot be wiped right now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Reason {
+    Mounted {
+        mount_point: PathBuf,
+        fs_type: String,
+    },
+    Swap,
+    HeldBy {
+        holder: String,
+    },
+}
+
+/// Something using the target disk, one of its partitions, or a device
+/// stacked on top of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blocker {
+    /// Kernel name of the busy device, such as `sda1` or `dm-0`.
+    pub device: String,
+    pub reason: Reason,
+}
+
+impl fmt::Display for Blocker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Reason::Mounted {
+                mount_point,
+                fs_type,
+            } => write!(
+                f,
+                "{} is mounted at {} ({})",
+                self.device,
+                mount_point.display(),
+                fs_type
+            ),
+            Reason::Swap => write!(f, "{} is active swap", self.device),
+            Reason::HeldBy { holder } => write!(f, "{} is held by {}", self.device, holder),
+        }
+    }
+}
+
+/// Decodes the octal escapes (`\040` for a space) used in mount tables.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let code = std::str::from_utf8(&bytes[i + 1..i + 4])
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 8).ok());
+            if let Some(code) = code {
+                out.push(code);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+impl SysRoot {
+    /// Parses `proc/self/mountinfo`, in mount order.
+    ///
+    /// Filesystems that report an anonymous device number (btrfs, for one)
+    /// are matched through their source device instead.
+    pub fn mounts(&self) -> Result<Vec<Mount>> {
+        let path = self.proc_path("self/mountinfo");
+        let contents = fs::read_to_string(&path).map_err(|e| Error::io(&path, "read", e))?;
+
+        let mut mounts = Vec::new();
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split(' ').collect();
+            let separator = match fields.iter().position(|&f| f == "-") {
+                Some(i) if i >= 6 && fields.len() >= i + 3 => i,
+                _ => continue,
+            };
+            let source = unescape(fields[separator + 2]);
+            let mut dev: DevId = match fields[2].parse() {
+                Ok(dev) => dev,
+                Err(()) => continue,
+            };
+            if dev.major == 0 {
+                if let Some(block) = source
+                    .strip_prefix("/dev/")
+                    .and_then(|rel| self.by_path(&self.dev_path(rel)))
+                {
+                    dev = block.dev;
+                }
+            }
+            mounts.push(Mount {
+                dev,
+                mount_point: PathBuf::from(unescape(fields[4])),
+                fs_type: fields[separator + 1].to_string(),
+                source,
+            });
+        }
+        Ok(mounts)
+    }
+
+    /// Block devices in use as swap, from `proc/swaps`.
+    pub fn swaps(&self) -> Result<Vec<BlockDev>> {
+        let path = self.proc_path("swaps");
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io(&path, "read", e)),
+        };
+        Ok(contents
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let name = unescape(fields.next()?);
+                match fields.next()? {
+                    "partition" => self.by_path(&self.dev_path(name.strip_prefix("/dev/")?)),
+                    _ => None,
+                }
+            })
+            .collect())
+    }
+
+    /// The disk `dev` followed by its partitions.
+    fn with_partitions(&self, dev: DevId) -> Result<Vec<BlockDev>> {
+        let mut devices = vec![BlockDev {
+            name: self.name_of(dev)?,
+            dev,
+        }];
+        devices.extend(self.partitions(dev)?);
+        Ok(devices)
+    }
+
+    /// Everything that keeps `dev` or its partitions busy: mounts, swap,
+    /// and stacked devices (LVM, dm-crypt, md) along with their own users.
+    pub fn blockers(&self, dev: DevId) -> Result<Vec<Blocker>> {
+        let mounts = self.mounts()?;
+        let swaps = self.swaps()?;
+
+        let mut blockers = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = self.with_partitions(dev)?;
+
+        while let Some(device) = pending.pop() {
+            if !seen.insert(device.dev) {
+                continue;
+            }
+            let name = self.display_name(&device);
+            for mount in mounts.iter().filter(|m| m.dev == device.dev) {
+                blockers.push(Blocker {
+                    device: name.clone(),
+                    reason: Reason::Mounted {
+                        mount_point: mount.mount_point.clone(),
+                        fs_type: mount.fs_type.clone(),
+                    },
+                });
+            }
+            if swaps.iter().any(|s| s.dev == device.dev) {
+                blockers.push(Blocker {
+                    device: name.clone(),
+                    reason: Reason::Swap,
+                });
+            }
+            for holder in self.holders(device.dev) {
+                blockers.push(Blocker {
+                    device: name.clone(),
+                    reason: Reason::HeldBy {
+                        holder: self.display_name(&holder),
+                    },
+                });
+                pending.push(holder);
+            }
+        }
+
+        blockers.sort_by(|a, b| a.device.cmp(&b.device));
+        Ok(blockers)
+    }
+
+    /// Unmounts every filesystem mounted directly from `dev` or one of its
+    /// partitions, most recent mount first. Swap and stacked devices are
+    /// left alone.
+    pub fn unmount_all(&self, dev: DevId) -> Result<()> {
+        let devices: HashSet<DevId> = self.with_partitions(dev)?.iter().map(|d| d.dev).collect();
+        for mount in self.mounts()?.iter().rev() {
+            if devices.contains(&mount.dev) {
+                sys::unmount(&mount.mount_point)
+                    .map_err(|e| Error::io(&mount.mount_point, "unmount", e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lists what keeps `device` busy on the running system. Regular files
+/// have no blockers.
+pub fn blockers(device: impl AsRef<Path>) -> Result<Vec<Blocker>> {
+    let device = device.as_ref();
+    if !is_block_device(device) {
+        return Ok(Vec::new());
+    }
+    SysRoot::default().blockers(DevId::of(device)?)
+}
+
+/// Returns true if `device` or one of its partitions is mounted.
+pub fn is_drive_mounted(device: impl AsRef<Path>) -> Result<bool> {
+    Ok(blockers(device)?
+        .iter()
+        .any(|b| matches!(b.reason, Reason::Mounted { .. })))
+}
+
+/// Unmounts every mount of `device` and its partitions with `umount2(2)`.
+pub fn unmount_drive(device: impl AsRef<Path>) -> Result<()> {
+    let device = device.as_ref();
+    if !is_block_device(device) {
+        return Ok(());
+    }
+    SysRoot::default().unmount_all(DevId::of(device)?)
+}
+
+fn is_block_device(device: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(device).is_ok_and(|m| m.file_type().is_block_device())
+}
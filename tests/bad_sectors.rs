@@ -0,0 +1,127 @@
+use std::time::Duration;
+use tempfile::TempDir;
+use wipers::{
+    BadSectorPolicy, Checkpoint, Error, FaultyTarget, JobStatus, MemoryTarget, RunSecret,
+    Verification, VerifyPolicy, WipeJob, WipeMethod,
+};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Skips bad sectors without waiting between retries.
+fn skip() -> BadSectorPolicy {
+    BadSectorPolicy::Skip {
+        retries: 2,
+        backoff: Duration::ZERO,
+    }
+}
+
+fn job(dir: &TempDir) -> WipeJob {
+    WipeJob::new(dir.path()).on_bad_sector(skip())
+}
+
+#[test]
+fn bad_sectors_are_narrowed_down_and_skipped() {
+    let dir = TempDir::new().unwrap();
+    let memory = MemoryTarget::new(3 * MIB);
+    memory.poke(0, &vec![0xaa; 3 * MIB as usize]);
+    let target = FaultyTarget::new(memory)
+        .eio_at(5)
+        .eio_at(3000)
+        .eio_at(3001)
+        .eio_at(3002);
+
+    let status = JobStatus::from(
+        job(&dir)
+            .verify(VerifyPolicy::AfterLastPass)
+            .run_on(&target, |_| {}),
+    );
+
+    let outcome = match status {
+        JobStatus::CompletedWithBadSectors(outcome) => outcome,
+        other => panic!("expected unwritable sectors, got {:?}", other),
+    };
+    assert_eq!(outcome.bad_sectors, vec![5..6, 3000..3003]);
+    assert_eq!(outcome.bytes_written, 3 * MIB - 4 * 512);
+    assert_eq!(outcome.bytes_verified, 3 * MIB - 4 * 512);
+    assert_eq!(outcome.verification, Verification::Passed);
+
+    // Every other sector was written; the bad ones kept their old contents.
+    let contents = target.inner().contents();
+    for (lba, sector) in contents.chunks(512).enumerate() {
+        let bad = lba == 5 || (3000..3003).contains(&lba);
+        let expected = if bad { 0xaa } else { 0 };
+        assert!(sector.iter().all(|&b| b == expected), "LBA {}", lba);
+    }
+}
+
+#[test]
+fn bad_sectors_are_narrowed_to_the_logical_sector() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::with_sectors(MIB, 512, 4096)).eio_at(9);
+
+    let outcome = job(&dir).run_on(&target, |_| {}).unwrap();
+
+    assert_eq!(outcome.bad_sectors, vec![9..10]);
+    assert_eq!(outcome.bytes_written, MIB - 512);
+}
+
+#[test]
+fn abort_policy_reports_where_the_write_failed() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB)).eio_at(5000);
+
+    let error = WipeJob::new(dir.path())
+        .run_on(&target, |_| {})
+        .unwrap_err();
+
+    match &error {
+        Error::Io { offset, source, .. } => {
+            assert_eq!(*offset, Some(2 * MIB));
+            assert_eq!(source.raw_os_error(), Some(libc::EIO));
+        }
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+    assert!(error.to_string().contains("at byte 2097152"), "{}", error);
+}
+
+#[test]
+fn other_errors_are_not_skipped() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB)).disappear_after(MIB);
+
+    let status = JobStatus::from(job(&dir).run_on(&target, |_| {}));
+
+    assert!(matches!(status, JobStatus::Vanished(_)), "{:?}", status);
+}
+
+#[test]
+fn journal_keeps_bad_sectors_across_a_resume() {
+    let dir = TempDir::new().unwrap();
+    let journal = dir.path().join("journal.json");
+    let secret = RunSecret::generate();
+    let job = job(&dir)
+        .method(WipeMethod::standard("dod").unwrap())
+        .secret(secret.clone())
+        .journal(&journal)
+        .checkpoint_interval(MIB);
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB))
+        .eio_at(100)
+        .disappear_after(6 * MIB);
+
+    let status = JobStatus::from(job.run_on(&target, |_| {}));
+    assert!(matches!(status, JobStatus::Vanished(_)), "{:?}", status);
+    let checkpoint = Checkpoint::load(&journal).unwrap();
+    assert_eq!(checkpoint.pass, 2);
+    assert_eq!(checkpoint.bad_sectors, vec![100..101]);
+
+    // The sector is skipped after resuming, even though it now takes writes.
+    let target = target.into_inner();
+    let outcome = WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret)
+        .journal(&journal)
+        .run_on(&target, |_| {})
+        .unwrap();
+    assert_eq!(outcome.bad_sectors, vec![100..101]);
+    assert!(!journal.exists());
+}
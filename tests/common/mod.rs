@@ -0,0 +1,138 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::{NamedTempFile, TempDir};
+
+/// An image file of `size` bytes, none of them zero.
+pub fn image(size: usize) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    let junk: Vec<u8> = (0..size).map(|i| (i % 251) as u8 | 1).collect();
+    file.write_all(&junk).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+pub fn write(root: &Path, rel: &str, contents: &str) {
+    let path = root.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+pub fn block(root: &Path, name: &str, dev: &str, parent: Option<&str>) {
+    write(root, &format!("sys/class/block/{}/dev", name), dev);
+    let dir = match parent {
+        Some(disk) => format!("sys/dev/block/{}/{}", disk, name),
+        None => format!("sys/dev/block/{}", dev),
+    };
+    write(
+        root,
+        &format!("{}/uevent", dir),
+        &format!("DEVNAME={}\n", name),
+    );
+    write(root, &format!("{}/dev", dir), dev);
+    if parent.is_some() {
+        write(root, &format!("{}/partition", dir), "1");
+        write(
+            root,
+            &format!("sys/dev/block/{}/uevent", dev),
+            &format!("DEVNAME={}\n", name),
+        );
+    }
+}
+
+/// Adds `sys/block/<name>` as a link into `sys/devices/<device_path>`, the
+/// way the kernel lays it out, with the usual identity attributes.
+pub fn disk(root: &Path, name: &str, dev: &str, device_path: &str, attrs: &[(&str, &str)]) {
+    let rel = format!("sys/devices/{}/block/{}", device_path, name);
+    write(root, &format!("{}/dev", rel), dev);
+    for (attr, value) in attrs {
+        write(root, &format!("{}/{}", rel, attr), value);
+    }
+    fs::create_dir_all(root.join("sys/block")).unwrap();
+    std::os::unix::fs::symlink(root.join(&rel), root.join("sys/block").join(name)).unwrap();
+}
+
+/// Adds partition `name` inside the sysfs directory of `disk`, linked from
+/// `sys/class/block` and `sys/dev/block` the way the kernel lays it out.
+/// `start` and `size` are in 512-byte sectors.
+pub fn partition(root: &Path, disk: &str, name: &str, dev: &str, start: u64, size: u64) {
+    let dir = fs::canonicalize(root.join("sys/block").join(disk))
+        .unwrap()
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("dev"), format!("{}\n", dev)).unwrap();
+    fs::write(dir.join("partition"), "1\n").unwrap();
+    fs::write(dir.join("start"), format!("{}\n", start)).unwrap();
+    fs::write(dir.join("size"), format!("{}\n", size)).unwrap();
+    fs::write(dir.join("uevent"), format!("DEVNAME={}\n", name)).unwrap();
+    for link in ["sys/class/block", "sys/dev/block"] {
+        fs::create_dir_all(root.join(link)).unwrap();
+    }
+    std::os::unix::fs::symlink(&dir, root.join("sys/class/block").join(name)).unwrap();
+    std::os::unix::fs::symlink(&dir, root.join("sys/dev/block").join(dev)).unwrap();
+}
+
+/// sda has a mounted /boot on sda1, swap on sda2 and an LVM volume on sda3
+/// that is mounted at /. sdb is mounted at a path containing "/dev/sda".
+pub fn fake_system() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+
+    block(root, "sda", "8:0", None);
+    block(root, "sda1", "8:1", Some("8:0"));
+    block(root, "sda2", "8:2", Some("8:0"));
+    block(root, "sda3", "8:3", Some("8:0"));
+    block(root, "sdb", "8:16", None);
+    block(root, "sdb1", "8:17", Some("8:16"));
+    block(root, "dm-0", "253:0", None);
+    disk(
+        root,
+        "sda",
+        "8:0",
+        "pci0000:00/0000:00:1f.2/ata1/host0/target0:0:0/0:0:0:0",
+        &[
+            ("size", "7814037168\n"),
+            ("removable", "0\n"),
+            ("queue/logical_block_size", "512\n"),
+            ("queue/physical_block_size", "4096\n"),
+            ("queue/rotational", "1\n"),
+            ("device/model", "ST4000DM004-2CV1\n"),
+            ("device/wwid", "naa.5000C500B1234567\n"),
+            ("device/vpd_pg80", "\0\0\0\x08ZFN0ABCD"),
+        ],
+    );
+    disk(
+        root,
+        "sdb",
+        "8:16",
+        "pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host6/target6:0:0/6:0:0:0",
+        &[
+            ("size", "60063744\n"),
+            ("removable", "1\n"),
+            ("queue/logical_block_size", "512\n"),
+            ("queue/physical_block_size", "512\n"),
+            ("queue/rotational", "0\n"),
+            ("device/model", "Ultra Fit       \n"),
+        ],
+    );
+    write(root, "sys/class/block/dm-0/dm/name", "vg-root\n");
+    fs::create_dir_all(root.join("sys/dev/block/8:3/holders/dm-0")).unwrap();
+    fs::create_dir_all(root.join("sys/dev/block/253:0/slaves/sda3")).unwrap();
+
+    write(
+        root,
+        "proc/self/mountinfo",
+        "22 1 253:0 / / rw,relatime shared:1 - ext4 /dev/mapper/vg-root rw\n\
+         23 22 8:1 / /boot rw,relatime shared:2 - vfat /dev/sda1 rw\n\
+         24 22 8:17 / /mnt/dev/sda\\040backup rw,relatime - ext4 /dev/sdb1 rw\n\
+         25 22 0:21 / /proc rw - proc proc rw\n",
+    );
+    write(
+        root,
+        "proc/swaps",
+        "Filename\tType\tSize\tUsed\tPriority\n/dev/sda2 partition 1048572 0 -2\n",
+    );
+    dir
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::{fake_system, partition};
+use wipers::{DevId, PartitionOf, Reason, SysRoot, Transport};
+
+#[test]
+fn describes_sata_disk_from_sysfs() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let drive = sys.drive("sda").unwrap();
+
+    assert_eq!(drive.dev, DevId { major: 8, minor: 0 });
+    assert_eq!(drive.model.as_deref(), Some("ST4000DM004-2CV1"));
+    assert_eq!(drive.serial.as_deref(), Some("ZFN0ABCD"));
+    assert_eq!(drive.wwn.as_deref(), Some("0x5000c500b1234567"));
+    assert_eq!(drive.size, 7814037168 * 512);
+    assert_eq!(drive.logical_sector_size, 512);
+    assert_eq!(drive.physical_sector_size, 4096);
+    assert!(drive.rotational);
+    assert!(!drive.removable);
+    assert_eq!(drive.transport, Transport::Sata);
+    assert_eq!(drive.partitions, ["sda1", "sda2", "sda3"]);
+    assert!(drive.is_mounted());
+    assert!(drive
+        .blockers
+        .iter()
+        .any(|b| b.device == "sda2" && b.reason == Reason::Swap));
+}
+
+#[test]
+fn describes_partition_through_its_disk() {
+    let dir = fake_system();
+    partition(dir.path(), "sda", "sda4", "8:4", 2048, 1_000_000);
+    let sys = SysRoot::new(dir.path());
+
+    let drive = sys.drive("sda4").unwrap();
+
+    assert_eq!(drive.dev, DevId { major: 8, minor: 4 });
+    assert_eq!(drive.size, 1_000_000 * 512);
+    assert_eq!(drive.serial.as_deref(), Some("ZFN0ABCD"));
+    assert_eq!(drive.model.as_deref(), Some("ST4000DM004-2CV1"));
+    assert_eq!(drive.physical_sector_size, 4096);
+    assert_eq!(drive.transport, Transport::Sata);
+    assert!(drive.partitions.is_empty());
+    assert_eq!(
+        drive.partition_of,
+        Some(PartitionOf {
+            disk: "sda".into(),
+            disk_dev: DevId { major: 8, minor: 0 },
+            start: 2048 * 512,
+        })
+    );
+    assert_eq!(drive.disk_name(), "sda");
+}
+
+#[test]
+fn lists_drives_sorted_with_transport() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let drives = sys.drives().unwrap();
+
+    let names: Vec<_> = drives.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["sda", "sdb"]);
+    let usb = &drives[1];
+    assert_eq!(usb.transport, Transport::Usb);
+    assert!(usb.removable);
+    assert_eq!(usb.model.as_deref(), Some("Ultra Fit"));
+    assert_eq!(usb.serial, None);
+}
+
+#[test]
+fn inventory_round_trips_through_json() {
+    let dir = fake_system();
+    let drives = SysRoot::new(dir.path()).drives().unwrap();
+
+    let json = serde_json::to_string(&drives).unwrap();
+    assert!(json.contains("\"dev\":\"8:0\""));
+    let parsed: Vec<wipers::Drive> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, drives);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::{fake_system, partition, write};
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use wipers::{Error, Identity, SysRoot};
+
+fn link(root: &Path, name: &str, target: &str) {
+    let dir = root.join("dev/disk/by-id");
+    fs::create_dir_all(&dir).unwrap();
+    let _ = fs::remove_file(dir.join(name));
+    symlink(format!("../../{}", target), dir.join(name)).unwrap();
+}
+
+fn sda_path(root: &Path, attr: &str) -> std::path::PathBuf {
+    fs::canonicalize(root.join("sys/block/sda"))
+        .unwrap()
+        .join(attr)
+}
+
+#[test]
+fn pins_wwn_link_as_stable_path() {
+    let dir = fake_system();
+    link(dir.path(), "ata-ST4000DM004-2CV1_ZFN0ABCD", "sda");
+    link(dir.path(), "wwn-0x5000c500b1234567", "sda");
+    let sys = SysRoot::new(dir.path());
+
+    let identity = Identity::of(&sys.drive("sda").unwrap());
+
+    assert_eq!(
+        identity.stable_path,
+        Path::new("/dev/disk/by-id/wwn-0x5000c500b1234567")
+    );
+    assert_eq!(identity.serial.as_deref(), Some("ZFN0ABCD"));
+    identity.check(&sys).unwrap();
+}
+
+#[test]
+fn falls_back_to_kernel_node_without_links() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let identity = Identity::of(&sys.drive("sdb").unwrap());
+
+    assert_eq!(identity.stable_path, Path::new("/dev/sdb"));
+    identity.check(&sys).unwrap();
+}
+
+#[test]
+fn detects_a_different_drive_behind_the_same_number() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+    let identity = Identity::of(&sys.drive("sda").unwrap());
+
+    fs::write(
+        sda_path(dir.path(), "device/vpd_pg80"),
+        "\0\0\0\x08ZFN0WXYZ",
+    )
+    .unwrap();
+
+    let err = identity.check(&sys).unwrap_err();
+    assert!(matches!(err, Error::IdentityChanged { .. }));
+    assert!(err.to_string().contains("ZFN0WXYZ"));
+}
+
+#[test]
+fn detects_a_size_change() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+    let identity = Identity::of(&sys.drive("sda").unwrap());
+
+    fs::write(sda_path(dir.path(), "size"), "1000\n").unwrap();
+
+    assert!(matches!(
+        identity.check(&sys),
+        Err(Error::IdentityChanged { .. })
+    ));
+}
+
+#[test]
+fn pins_a_partition_by_its_disk_and_extent() {
+    let dir = fake_system();
+    partition(dir.path(), "sda", "sda4", "8:4", 2048, 1_000_000);
+    link(dir.path(), "wwn-0x5000c500b1234567-part4", "sda4");
+    let sys = SysRoot::new(dir.path());
+
+    let identity = Identity::of(&sys.drive("sda4").unwrap());
+
+    assert_eq!(identity.dev, "8:4".parse().unwrap());
+    assert_eq!(identity.serial.as_deref(), Some("ZFN0ABCD"));
+    assert_eq!(identity.size, 1_000_000 * 512);
+    assert_eq!(identity.start, Some(2048 * 512));
+    assert_eq!(
+        identity.stable_path,
+        Path::new("/dev/disk/by-id/wwn-0x5000c500b1234567-part4")
+    );
+    identity.check(&sys).unwrap();
+
+    // Repartitioned: the same number now starts elsewhere on the disk.
+    fs::write(sda_path(dir.path(), "sda4/start"), "4096\n").unwrap();
+    let err = identity.check(&sys).unwrap_err();
+    assert!(err.to_string().contains("partition moved"), "{}", err);
+}
+
+#[test]
+fn detects_a_stable_link_moving_to_another_disk() {
+    let dir = fake_system();
+    link(dir.path(), "wwn-0x5000c500b1234567", "sda");
+    let sys = SysRoot::new(dir.path());
+    let identity = Identity::of(&sys.drive("sda").unwrap());
+
+    link(dir.path(), "wwn-0x5000c500b1234567", "sdb");
+
+    let err = identity.check(&sys).unwrap_err();
+    assert!(err.to_string().contains("now leads to sdb"));
+}
+
+#[test]
+fn detects_a_vanished_device() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+    let identity = Identity::of(&sys.drive("sdb").unwrap());
+
+    write(dir.path(), "sys/dev/block/8:16/uevent", "MAJOR=8\n");
+
+    assert!(matches!(
+        identity.check(&sys),
+        Err(Error::IdentityChanged { .. })
+    ));
+}
+
+#[test]
+fn relocates_a_renumbered_drive_through_its_stable_link() {
+    let dir = fake_system();
+    link(dir.path(), "wwn-0x5000c500b1234567", "sda");
+    let sys = SysRoot::new(dir.path());
+    let now = Identity::of(&sys.drive("sda").unwrap());
+    // Pinned while the drive was sdc, before a power cycle.
+    let pinned = Identity {
+        name: "sdc".into(),
+        dev: "8:32".parse().unwrap(),
+        ..now.clone()
+    };
+    assert!(pinned.check(&sys).is_err());
+
+    let relocated = pinned.relocate(&sys).unwrap();
+
+    assert_eq!(relocated, now);
+    relocated.check(&sys).unwrap();
+}
+
+#[test]
+fn relocation_refuses_a_different_drive_behind_the_link() {
+    let dir = fake_system();
+    link(dir.path(), "wwn-0x5000c500b1234567", "sda");
+    let sys = SysRoot::new(dir.path());
+    let pinned = Identity::of(&sys.drive("sda").unwrap());
+    link(dir.path(), "wwn-0x5000c500b1234567", "sdb");
+
+    let err = pinned.relocate(&sys).unwrap_err();
+
+    assert!(matches!(err, Error::IdentityChanged { .. }), "{:?}", err);
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use common::image;
+use std::fs;
+use std::os::unix::fs::{FileExt, MetadataExt};
+use tempfile::NamedTempFile;
+use wipers::{
+    DeviceReport, JobStatus, RunSecret, SparseMode, Verification, VerifyPolicy, WipeJob, WipeMethod,
+};
+
+#[test]
+fn zero_wipe_covers_odd_sized_image_exactly() {
+    let size = 3 * 1024 * 1024 + 12345;
+    let img = image(size);
+
+    let outcome = WipeJob::new(img.path())
+        .verify(VerifyPolicy::AfterLastPass)
+        .run(|_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bytes_per_pass, size as u64);
+    assert_eq!(outcome.bytes_written, size as u64);
+    assert_eq!(outcome.bytes_verified, size as u64);
+    assert_eq!(outcome.verification, Verification::Passed);
+
+    let contents = fs::read(img.path()).unwrap();
+    assert_eq!(contents.len(), size);
+    assert!(contents.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn image_smaller_than_one_chunk_is_written_in_full() {
+    let size = 1000;
+    let img = image(size);
+
+    let outcome = WipeJob::new(img.path())
+        .method(WipeMethod::standard("vsitr").unwrap())
+        .run(|_| {})
+        .unwrap();
+
+    assert_eq!(outcome.passes, 7);
+    assert_eq!(outcome.bytes_written, 7 * size as u64);
+    let contents = fs::read(img.path()).unwrap();
+    assert_eq!(contents, vec![0xAA; size]);
+}
+
+#[test]
+fn random_and_complement_passes_verify_on_odd_sizes() {
+    let size = 2 * 1024 * 1024 + 7;
+    let img = image(size);
+    let method = WipeMethod::from_toml(
+        r#"
+        name = "random then complement"
+
+        [[pass]]
+        type = "random"
+        verify = true
+
+        [[pass]]
+        type = "complement"
+        verify = true
+        "#,
+    )
+    .unwrap();
+
+    let outcome = WipeJob::new(img.path())
+        .method(method)
+        .rounds(2)
+        .run(|_| {})
+        .unwrap();
+
+    assert_eq!(outcome.passes, 4);
+    assert_eq!(outcome.bytes_verified, 4 * size as u64);
+    assert_eq!(fs::metadata(img.path()).unwrap().len(), size as u64);
+}
+
+#[test]
+fn random_passes_are_reproducible_from_the_secret() {
+    let size = 1024 * 1024 + 513;
+    let secret = RunSecret::from_bytes([7; 32]);
+    let first = image(size);
+    let second = image(size);
+
+    for img in [&first, &second] {
+        WipeJob::new(img.path())
+            .method(WipeMethod::random())
+            .secret(secret.clone())
+            .run(|_| {})
+            .unwrap();
+    }
+
+    let a = fs::read(first.path()).unwrap();
+    let b = fs::read(second.path()).unwrap();
+    assert_eq!(a, b);
+    assert!(a.iter().any(|&b| b != 0));
+}
+
+#[test]
+fn lba_stamp_marks_each_sector_with_its_number() {
+    let size = 3 * 512 + 100;
+    let img = image(size);
+    let method = WipeMethod::from_toml("name = \"lba\"\n[[pass]]\ntype = \"lba\"\n").unwrap();
+
+    WipeJob::new(img.path()).method(method).run(|_| {}).unwrap();
+
+    let contents = fs::read(img.path()).unwrap();
+    for (lba, sector) in contents.chunks(512).enumerate() {
+        let stamp = (lba as u64).to_le_bytes();
+        assert!(sector.chunks(8).all(|word| word == &stamp[..word.len()]));
+    }
+}
+
+#[test]
+fn dry_run_runs_every_pass_without_writing() {
+    let size = 2 * 1024 * 1024 + 512;
+    let img = image(size);
+    let before = fs::read(img.path()).unwrap();
+
+    let outcome = WipeJob::new(img.path())
+        .method(WipeMethod::standard("dod").unwrap())
+        .verify(VerifyPolicy::AfterLastPass)
+        .dry_run(true)
+        .run(|_| {})
+        .unwrap();
+
+    assert!(outcome.dry_run);
+    assert_eq!(outcome.passes, 3);
+    assert_eq!(outcome.bytes_written, 3 * size as u64);
+    assert_eq!(outcome.bytes_verified, size as u64);
+    assert_eq!(outcome.verification, Verification::DryRun);
+    assert_eq!(fs::read(img.path()).unwrap(), before);
+
+    let report = DeviceReport::new(img.path(), None, &JobStatus::Wiped(outcome));
+    assert_eq!(report.status, "dry-run");
+    assert!(!report.verified);
+}
+
+#[test]
+fn estimate_counts_written_and_verified_passes() {
+    let job = WipeJob::new("/dev/null")
+        .method(WipeMethod::standard("dod").unwrap())
+        .rounds(2)
+        .verify(VerifyPolicy::AfterLastPass);
+
+    let estimate = job.estimate(1_000_000, 100_000).unwrap();
+
+    // dod verifies its last pass, once per round.
+    assert_eq!(estimate.bytes_written, 6_000_000);
+    assert_eq!(estimate.bytes_verified, 2_000_000);
+    assert_eq!(estimate.duration.as_secs(), 80);
+}
+
+/// A sparse image with random-looking data in two separate extents.
+fn sparse_image(size: u64) -> NamedTempFile {
+    let file = NamedTempFile::new().unwrap();
+    file.as_file().set_len(size).unwrap();
+    let junk = vec![0x5a; 256 * 1024];
+    file.as_file().write_all_at(&junk, 1024 * 1024).unwrap();
+    file.as_file()
+        .write_all_at(&junk, size - 512 * 1024)
+        .unwrap();
+    file.as_file().sync_all().unwrap();
+    file
+}
+
+#[test]
+fn extents_mode_overwrites_only_data_and_leaves_the_file_sparse() {
+    let size = 16 * 1024 * 1024;
+    let img = sparse_image(size);
+
+    let outcome = WipeJob::new(img.path())
+        .method(WipeMethod::standard("dod").unwrap())
+        .sparse(SparseMode::Extents)
+        .run(|_| {})
+        .unwrap();
+
+    assert!(outcome.bytes_per_pass >= 512 * 1024);
+    assert!(outcome.bytes_per_pass < size);
+    assert_eq!(outcome.bytes_written, 3 * outcome.bytes_per_pass);
+    let metadata = fs::metadata(img.path()).unwrap();
+    assert_eq!(metadata.len(), size);
+    assert_eq!(metadata.blocks(), 0);
+    assert!(fs::read(img.path()).unwrap().iter().all(|&b| b == 0));
+}
+
+#[test]
+fn allocate_mode_fills_the_holes() {
+    let size = 4 * 1024 * 1024;
+    let img = sparse_image(size);
+
+    let outcome = WipeJob::new(img.path())
+        .sparse(SparseMode::Allocate)
+        .run(|_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bytes_per_pass, size);
+    let metadata = fs::metadata(img.path()).unwrap();
+    assert!(metadata.blocks() * 512 >= size);
+    assert!(fs::read(img.path()).unwrap().iter().all(|&b| b == 0));
+}
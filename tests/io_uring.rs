@@ -0,0 +1,156 @@
+//! The io_uring backend must leave targets exactly as the synchronous one
+//! does. Without the `io-uring` feature, or where the kernel does not allow
+//! it, the tests that need a ring are skipped.
+
+mod common;
+
+use common::image;
+use std::fs;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+use wipers::{
+    BadSectorPolicy, Error, FaultyTarget, IoBackend, MemoryTarget, RunSecret, SparseMode,
+    Verification, VerifyPolicy, WipeJob, WipeMethod, WipeOutcome,
+};
+
+const MIB: usize = 1024 * 1024;
+
+/// Whether a ring can be set up here, saying so when a test is skipped.
+fn ring_available() -> bool {
+    let available = IoBackend::IoUring.is_available();
+    if !available {
+        eprintln!("io_uring is not available in this build or on this kernel; skipping");
+    }
+    available
+}
+
+/// Runs the job `job` makes for a path on a fresh image of `size` bytes
+/// with each backend, and returns the outcome and resulting contents.
+fn with_each_backend(size: usize, job: impl Fn(&Path) -> WipeJob) -> [(WipeOutcome, Vec<u8>); 2] {
+    [IoBackend::Sync, IoBackend::IoUring].map(|backend| {
+        let img = image(size);
+        let outcome = job(img.path()).io_backend(backend).run(|_| {}).unwrap();
+        (outcome, fs::read(img.path()).unwrap())
+    })
+}
+
+fn assert_same(sync: &WipeOutcome, uring: &WipeOutcome) {
+    assert_eq!(sync.bytes_per_pass, uring.bytes_per_pass);
+    assert_eq!(sync.bytes_written, uring.bytes_written);
+    assert_eq!(sync.bytes_verified, uring.bytes_verified);
+    assert_eq!(sync.verification, uring.verification);
+    assert_eq!(sync.bad_sectors, uring.bad_sectors);
+    assert_eq!(sync.io_backend, IoBackend::Sync);
+    assert_eq!(uring.io_backend, IoBackend::IoUring);
+}
+
+#[test]
+fn io_uring_wipes_images_like_the_sync_backend() {
+    if !ring_available() {
+        return;
+    }
+    let secret = RunSecret::generate();
+    let size = 3 * MIB + 12345;
+
+    let [(sync, synced), (uring, ringed)] = with_each_backend(size, |path| {
+        WipeJob::new(path)
+            .method(WipeMethod::standard("dod").unwrap())
+            .secret(secret.clone())
+            .verify(VerifyPolicy::EveryPass)
+            .block_size(96 * 1024)
+            .queue_depth(5)
+    });
+
+    assert_same(&sync, &uring);
+    assert_eq!(uring.bytes_written, 3 * size as u64);
+    assert_eq!(uring.verification, Verification::Passed);
+    assert!(synced == ringed);
+}
+
+#[test]
+fn io_uring_wipes_the_extents_of_sparse_images_like_the_sync_backend() {
+    if !ring_available() {
+        return;
+    }
+    let secret = RunSecret::generate();
+    let size = 4 * MIB;
+
+    let [(sync, synced), (uring, ringed)] = [IoBackend::Sync, IoBackend::IoUring].map(|backend| {
+        let img = NamedTempFile::new().unwrap();
+        img.as_file().set_len(size as u64).unwrap();
+        img.as_file()
+            .write_all_at(&[0xaa; MIB], MIB as u64)
+            .unwrap();
+        img.as_file()
+            .write_all_at(&[0xaa; 5000], 3 * MIB as u64)
+            .unwrap();
+        let outcome = WipeJob::new(img.path())
+            .method(WipeMethod::standard("random").unwrap())
+            .secret(secret.clone())
+            .sparse(SparseMode::Extents)
+            .verify(VerifyPolicy::AfterLastPass)
+            .queue_depth(3)
+            .io_backend(backend)
+            .run(|_| {})
+            .unwrap();
+        (outcome, fs::read(img.path()).unwrap())
+    });
+
+    assert_same(&sync, &uring);
+    assert!(synced == ringed);
+}
+
+#[test]
+fn io_uring_verification_finds_the_same_mismatch() {
+    if !ring_available() {
+        return;
+    }
+    let size = 2 * MIB + 100;
+    let img = image(size);
+    let job = WipeJob::new(img.path())
+        .io_backend(IoBackend::IoUring)
+        .queue_depth(4);
+    job.run(|_| {}).unwrap();
+    let outcome = job.verify_only(|_| {}).unwrap();
+    assert_eq!(outcome.io_backend, IoBackend::IoUring);
+    img.as_file().write_all_at(&[1], MIB as u64 + 77).unwrap();
+
+    for backend in [IoBackend::Sync, IoBackend::IoUring] {
+        let error = WipeJob::new(img.path())
+            .block_size(256 * 1024)
+            .queue_depth(4)
+            .io_backend(backend)
+            .verify_only(|_| {})
+            .unwrap_err();
+        match error {
+            Error::VerifyMismatch { offset, .. } => assert_eq!(offset, MIB as u64 + 77),
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn io_uring_falls_back_for_targets_without_a_descriptor() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB as u64))
+        .eio_at(10)
+        .eio_at(4100);
+
+    let outcome = WipeJob::new(dir.path())
+        .io_backend(IoBackend::IoUring)
+        .queue_depth(4)
+        .on_bad_sector(BadSectorPolicy::Skip {
+            retries: 0,
+            backoff: Duration::ZERO,
+        })
+        .verify(VerifyPolicy::AfterLastPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bad_sectors, vec![10..11, 4100..4101]);
+    assert_eq!(outcome.bytes_written, 4 * MIB as u64 - 1024);
+    assert_eq!(outcome.verification, Verification::Passed);
+    assert_eq!(outcome.io_backend, IoBackend::Sync);
+}
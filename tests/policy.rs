@@ -0,0 +1,107 @@
+mod common;
+
+use common::{fake_system, partition, write};
+use wipers::{DevId, Error, Policy, SysRoot};
+
+#[test]
+fn finds_disks_behind_root_boot_and_swap() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let system = sys.system_disks().unwrap();
+
+    assert_eq!(system.len(), 1);
+    let sda = &system[0];
+    assert_eq!(sda.name, "sda");
+    assert_eq!(sda.dev, DevId { major: 8, minor: 0 });
+    assert!(sda.uses.iter().any(|u| u.starts_with("/ ")));
+    assert!(sda.uses.contains(&"/boot (sda1)".to_string()));
+    assert!(sda.uses.contains(&"swap (sda2)".to_string()));
+}
+
+#[test]
+fn resolves_kernel_root_by_uuid() {
+    let dir = fake_system();
+    let root = dir.path();
+    write(root, "proc/self/mountinfo", "");
+    write(root, "proc/swaps", "Filename\tType\tSize\tUsed\tPriority\n");
+    write(
+        root,
+        "proc/cmdline",
+        "BOOT_IMAGE=/vmlinuz root=UUID=1234-abcd ro quiet\n",
+    );
+    std::fs::create_dir_all(root.join("dev/disk/by-uuid")).unwrap();
+    write(root, "dev/sdb1", "");
+    std::os::unix::fs::symlink("../../sdb1", root.join("dev/disk/by-uuid/1234-abcd")).unwrap();
+    let sys = SysRoot::new(root);
+
+    let system = sys.system_disks().unwrap();
+
+    let names: Vec<_> = system.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["sdb"]);
+}
+
+#[test]
+fn refuses_system_disk_unless_named_explicitly() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+    let system = sys.system_disks().unwrap();
+    let sda = sys.drive("sda").unwrap();
+    let sdb = sys.drive("sdb").unwrap();
+    let policy = Policy::default();
+
+    assert!(matches!(
+        policy.check(&sda, &system, &[]),
+        Err(Error::Protected { .. })
+    ));
+    assert!(policy.check(&sda, &system, &["sdb".into()]).is_err());
+    assert!(policy.check(&sda, &system, &["sda".into()]).is_ok());
+    assert!(policy.check(&sdb, &system, &[]).is_ok());
+}
+
+#[test]
+fn refuses_partitions_of_system_disks() {
+    let dir = fake_system();
+    partition(dir.path(), "sda", "sda4", "8:4", 2048, 1_000_000);
+    let sys = SysRoot::new(dir.path());
+    let system = sys.system_disks().unwrap();
+    let sda4 = sys.drive("sda4").unwrap();
+    let policy = Policy::default();
+
+    assert!(matches!(
+        policy.check(&sda4, &system, &["sda4".into()]),
+        Err(Error::Protected { .. })
+    ));
+    assert!(policy.check(&sda4, &system, &["sda".into()]).is_ok());
+}
+
+#[test]
+fn applies_deny_and_allow_rules() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+    let sda = sys.drive("sda").unwrap();
+    let sdb = sys.drive("sdb").unwrap();
+    let overrides = ["sda".to_string()];
+
+    let deny = Policy::from_toml("[deny]\nwwn = [\"0x5000C500B1234567\"]\n").unwrap();
+    let err = deny.check(&sda, &[], &overrides).unwrap_err();
+    assert!(err.to_string().contains("wwn"));
+    assert!(deny.check(&sdb, &[], &[]).is_ok());
+
+    let allow = Policy::from_toml("[allow]\nmodel = [\"ST4000*\"]\n").unwrap();
+    assert!(allow.check(&sda, &[], &[]).is_ok());
+    assert!(allow.check(&sdb, &[], &[]).is_err());
+
+    let both =
+        Policy::from_toml("[allow]\nmodel = [\"*\"]\n[deny]\nserial = [\"ZFN0*\"]\n").unwrap();
+    assert!(both.check(&sda, &[], &[]).is_err());
+    assert!(both.check(&sdb, &[], &[]).is_ok());
+}
+
+#[test]
+fn rejects_unknown_policy_keys() {
+    assert!(matches!(
+        Policy::from_toml("[deny]\nvendor = [\"*\"]\n"),
+        Err(Error::InvalidPolicy(_))
+    ));
+}
@@ -0,0 +1,94 @@
+use std::io::Write;
+use tempfile::NamedTempFile;
+use wipers::{PartitionTable, Preview, Signature, SysRoot};
+
+const SIZE: usize = 1024 * 1024;
+
+/// A zeroed image with `bytes` written at each offset.
+fn image(patches: &[(usize, &[u8])]) -> NamedTempFile {
+    let mut data = vec![0u8; SIZE];
+    for (offset, bytes) in patches {
+        data[*offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&data).unwrap();
+    file
+}
+
+fn preview(patches: &[(usize, &[u8])]) -> Preview {
+    let img = image(patches);
+    SysRoot::default().preview(img.path(), None).unwrap()
+}
+
+fn signature(kind: &str, label: Option<&str>) -> Option<Signature> {
+    Some(Signature {
+        kind: kind.to_string(),
+        label: label.map(str::to_string),
+    })
+}
+
+#[test]
+fn blank_image_has_nothing() {
+    let preview = preview(&[]);
+
+    assert_eq!(preview.size, SIZE as u64);
+    assert_eq!(preview.table, None);
+    assert_eq!(preview.signature, None);
+    assert!(preview.partitions.is_empty());
+}
+
+#[test]
+fn detects_partition_tables() {
+    let mbr = preview(&[(510, &[0x55, 0xaa])]);
+    assert_eq!(mbr.table, Some(PartitionTable::Mbr));
+
+    let gpt = preview(&[(510, &[0x55, 0xaa]), (512, b"EFI PART")]);
+    assert_eq!(gpt.table, Some(PartitionTable::Gpt));
+}
+
+#[test]
+fn detects_ext4_with_label() {
+    let preview = preview(&[
+        (1024 + 56, &[0x53, 0xef]),
+        (1024 + 96, &[0x40, 0, 0, 0]),
+        (1024 + 120, b"backup\0"),
+    ]);
+
+    assert_eq!(preview.signature, signature("ext4", Some("backup")));
+}
+
+#[test]
+fn detects_fat32_and_not_an_mbr() {
+    let preview = preview(&[
+        (71, b"USBSTICK   "),
+        (82, b"FAT32   "),
+        (510, &[0x55, 0xaa]),
+    ]);
+
+    assert_eq!(preview.signature, signature("vfat", Some("USBSTICK")));
+    assert_eq!(preview.table, None);
+}
+
+#[test]
+fn detects_swap_with_label() {
+    let preview = preview(&[(1024 + 28, b"swap0"), (4086, b"SWAPSPACE2")]);
+
+    assert_eq!(preview.signature, signature("swap", Some("swap0")));
+}
+
+#[test]
+fn detects_volume_members() {
+    let lvm = preview(&[(512, b"LABELONE"), (512 + 24, b"LVM2 001")]);
+    assert_eq!(lvm.signature, signature("LVM2_member", None));
+
+    let md = preview(&[
+        (4096, &0xa92b_4efcu32.to_le_bytes()),
+        (4100, &1u32.to_le_bytes()),
+        (4096 + 32, b"host:0"),
+    ]);
+    assert_eq!(md.signature, signature("linux_raid_member", Some("host:0")));
+
+    let luks = preview(&[(0, b"LUKS\xba\xbe\0\x02"), (24, b"vault")]);
+    assert_eq!(luks.signature, signature("crypto_LUKS", Some("vault")));
+    assert!(luks.signature.unwrap().is_member());
+}
@@ -0,0 +1,318 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tempfile::TempDir;
+use wipers::{
+    Checkpoint, Error, Event, FaultyTarget, IoBackend, JobStatus, MemoryTarget, Phase, RunSecret,
+    Verification, VerifyPolicy, WipeJob, WipeMethod,
+};
+
+const MIB: u64 = 1024 * 1024;
+
+/// The secret of every random job here, as the operator would keep it with
+/// `--export-seed`; journals do not hold it.
+fn secret() -> RunSecret {
+    RunSecret::from_bytes([7; 32])
+}
+
+/// Writes one chunk at a time, so that interruptions land on exact offsets.
+fn random_job(dir: &TempDir) -> WipeJob {
+    WipeJob::new(dir.path())
+        .method(WipeMethod::standard("dod").unwrap())
+        .verify(VerifyPolicy::AfterLastPass)
+        .secret(secret())
+        .journal(dir.path().join("journal.json"))
+        .checkpoint_interval(MIB)
+        .queue_depth(1)
+}
+
+/// Runs `job` until the target disappears after `bytes` have been written,
+/// returning the target and the journal left behind.
+fn interrupt(job: &WipeJob, size: u64, bytes: u64) -> (MemoryTarget, Checkpoint) {
+    let target = FaultyTarget::new(MemoryTarget::new(size)).disappear_after(bytes);
+    let status = JobStatus::from(job.run_on(&target, |_| {}));
+    assert!(matches!(status, JobStatus::Vanished(_)), "{:?}", status);
+    (
+        target.into_inner(),
+        Checkpoint::load(job_journal(job)).unwrap(),
+    )
+}
+
+fn job_journal(job: &WipeJob) -> std::path::PathBuf {
+    job.target().join("journal.json")
+}
+
+#[test]
+fn completed_wipe_removes_its_journal() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+
+    job.run_on(&MemoryTarget::new(2 * MIB), |_| {}).unwrap();
+
+    assert!(!job_journal(&job).exists());
+}
+
+#[test]
+fn journal_records_the_last_flushed_offset() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+
+    // Three passes of 4 MiB; the device goes away 2.5 MiB into the second.
+    let (_, checkpoint) = interrupt(&job, 4 * MIB, 6 * MIB + MIB / 2);
+
+    assert_eq!(checkpoint.pass, 2);
+    assert_eq!(checkpoint.passes, 3);
+    assert_eq!(checkpoint.phase, Phase::Write);
+    assert_eq!(checkpoint.offset, 2 * MIB);
+    assert_eq!(checkpoint.bytes_written, 6 * MIB);
+    assert_eq!(checkpoint.interruptions, 0);
+}
+
+#[test]
+fn resumed_wipe_continues_where_it_stopped() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+    // The last pass of dod is random, so its stream must be resumed exactly.
+    let (target, checkpoint) = interrupt(&job, 4 * MIB, 9 * MIB + 100);
+    assert_eq!(checkpoint.pass, 3);
+    assert_eq!(checkpoint.offset, MIB);
+    assert_eq!(
+        checkpoint.rng.map(|rng| rng.word),
+        Some(MIB / 4),
+        "random pass records its stream position"
+    );
+
+    let outcome = WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .journal(job_journal(&job))
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    let resumed = outcome.resumed.unwrap();
+    assert_eq!((resumed.pass, resumed.offset), (3, MIB));
+    assert_eq!(resumed.interruptions, 1);
+    assert_eq!(outcome.verification, Verification::Passed);
+    // Nothing before the checkpoint was written again.
+    assert_eq!(outcome.bytes_written, 12 * MIB);
+    assert!(!job_journal(&job).exists());
+
+    // The whole target holds the final random pass.
+    job.verify_on(&target, |_| {}).unwrap();
+}
+
+#[test]
+fn writers_in_flight_checkpoint_only_what_is_written() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir).block_size(256 * 1024).queue_depth(4);
+    // The writes of the last pass finish in any order; whatever the order,
+    // the journal may only claim the part before the first unfinished one.
+    let (target, checkpoint) = interrupt(&job, 4 * MIB, 9 * MIB + 100);
+    assert_eq!(checkpoint.pass, 3);
+    assert_eq!(checkpoint.offset % (256 * 1024), 0);
+    assert!(checkpoint.offset <= MIB + 100);
+    assert_eq!(checkpoint.bytes_written, 8 * MIB + checkpoint.offset);
+
+    WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    job.verify_on(&target, |_| {}).unwrap();
+}
+
+#[test]
+fn resume_refuses_a_journal_for_another_target() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+    let (_, checkpoint) = interrupt(&job, 4 * MIB, 5 * MIB);
+
+    let smaller = MemoryTarget::new(2 * MIB);
+    let err = WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .run_on(&smaller, |_| {})
+        .unwrap_err();
+
+    assert!(matches!(err, Error::InvalidJob(_)), "{:?}", err);
+    assert!(smaller.contents().iter().all(|&b| b == 0));
+}
+
+#[test]
+fn resuming_random_passes_needs_the_run_secret() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+    let (target, checkpoint) = interrupt(&job, 4 * MIB, 5 * MIB);
+
+    let journal = std::fs::read_to_string(job_journal(&job)).unwrap();
+    assert!(!journal.contains(&secret().to_hex()));
+    assert!(checkpoint.needs_secret());
+    assert!(checkpoint.matches_secret(&secret()));
+
+    for other in [None, Some(RunSecret::generate())] {
+        let mut resumed = WipeJob::resume(checkpoint.clone()).unwrap();
+        if let Some(other) = other {
+            resumed = resumed.secret(other);
+        }
+        let err = resumed.run_on(&target, |_| {}).unwrap_err();
+        assert!(matches!(err, Error::InvalidJob(_)), "{:?}", err);
+    }
+}
+
+#[test]
+fn interrupted_resume_counts_every_interruption() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+    let (_, checkpoint) = interrupt(&job, 4 * MIB, 3 * MIB);
+
+    let resumed = WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .journal(job_journal(&job))
+        .checkpoint_interval(MIB);
+    // The rest of the first pass, then a chunk of the second makes it.
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB)).disappear_after(4 * MIB);
+    let status = JobStatus::from(resumed.run_on(&target, |_| {}));
+    assert!(matches!(status, JobStatus::Vanished(_)));
+
+    let checkpoint = Checkpoint::load(job_journal(&job)).unwrap();
+    assert_eq!(checkpoint.interruptions, 1);
+    assert_eq!((checkpoint.pass, checkpoint.offset), (2, MIB));
+}
+
+#[test]
+fn stopped_job_checkpoints_where_it_stopped() {
+    let dir = TempDir::new().unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    // No periodic checkpoints: only the stop records the position.
+    let job = random_job(&dir)
+        .checkpoint_interval(u64::MAX)
+        .stop_when(stop.clone());
+    let target = MemoryTarget::new(4 * MIB);
+
+    let result = job.run_on(&target, |event| {
+        if let Event::Progress {
+            pass: 2, written, ..
+        } = event
+        {
+            if *written >= 3 * MIB {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(err, Error::Interrupted { pass: 2, offset, .. } if offset == 3 * MIB),
+        "{:?}",
+        err
+    );
+    let checkpoint = Checkpoint::load(job_journal(&job)).unwrap();
+    assert_eq!((checkpoint.pass, checkpoint.offset), (2, 3 * MIB));
+    assert_eq!(checkpoint.bytes_written, 7 * MIB);
+
+    let outcome = WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .run_on(&target, |_| {})
+        .unwrap();
+    assert_eq!(outcome.verification, Verification::Passed);
+    assert_eq!(outcome.bytes_written, 12 * MIB);
+}
+
+#[test]
+fn job_stopped_while_verifying_a_random_pass_resumes() {
+    let dir = TempDir::new().unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    let job = random_job(&dir).stop_when(stop.clone());
+    let target = MemoryTarget::new(4 * MIB);
+
+    let result = job.run_on(&target, |event| {
+        if let Event::VerifyStarted { .. } = event {
+            stop.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(err, Error::Interrupted { pass: 3, .. }),
+        "{:?}",
+        err
+    );
+    let checkpoint = Checkpoint::load(job_journal(&job)).unwrap();
+    assert_eq!((checkpoint.pass, checkpoint.phase), (3, Phase::Verify));
+    assert_eq!(checkpoint.rng, None);
+
+    let outcome = WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .journal(job_journal(&job))
+        .run_on(&target, |_| {})
+        .unwrap();
+    assert_eq!(outcome.verification, Verification::Passed);
+    assert_eq!(outcome.bytes_written, 12 * MIB);
+    assert_eq!(outcome.bytes_verified, 4 * MIB);
+    assert!(!job_journal(&job).exists());
+}
+
+#[test]
+fn stop_is_reported_as_interrupted() {
+    let dir = TempDir::new().unwrap();
+    let stop = Arc::new(AtomicBool::new(true));
+
+    let status = JobStatus::from(
+        WipeJob::new(dir.path())
+            .stop_when(stop)
+            .run_on(&MemoryTarget::new(MIB), |_| {}),
+    );
+
+    assert!(matches!(status, JobStatus::Interrupted(_)), "{:?}", status);
+}
+
+#[test]
+fn resume_keeps_the_io_settings_it_started_with() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir)
+        .block_size(256 * 1024)
+        .queue_depth(3)
+        .io_backend(IoBackend::IoUring)
+        .direct_io(false);
+    let (_, checkpoint) = interrupt(&job, 4 * MIB, 5 * MIB);
+    let settings = |c: &Checkpoint| (c.block_size, c.queue_depth, c.io_backend, c.direct_io);
+    assert_eq!(
+        settings(&checkpoint),
+        (256 * 1024, 3, IoBackend::IoUring, false)
+    );
+
+    // Interrupted again, the resumed job records the same settings.
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB)).disappear_after(4 * MIB);
+    let status = JobStatus::from(
+        WipeJob::resume(checkpoint.clone())
+            .unwrap()
+            .secret(secret())
+            .journal(job_journal(&job))
+            .run_on(&target, |_| {}),
+    );
+    assert!(matches!(status, JobStatus::Vanished(_)), "{:?}", status);
+    let again = Checkpoint::load(job_journal(&job)).unwrap();
+    assert_eq!(settings(&again), settings(&checkpoint));
+}
+
+#[test]
+fn resume_may_use_another_block_size() {
+    let dir = TempDir::new().unwrap();
+    let job = random_job(&dir);
+    let (target, checkpoint) = interrupt(&job, 4 * MIB, 9 * MIB + 100);
+    assert_eq!((checkpoint.pass, checkpoint.offset), (3, MIB));
+
+    // The checkpoint falls in the middle of a 768 KiB block.
+    WipeJob::resume(checkpoint)
+        .unwrap()
+        .secret(secret())
+        .block_size(768 * 1024)
+        .queue_depth(3)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    job.verify_on(&target, |_| {}).unwrap();
+}
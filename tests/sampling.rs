@@ -0,0 +1,100 @@
+use tempfile::TempDir;
+use wipers::{Error, MemoryTarget, Sampling, Verification, VerifyPolicy, WipeJob, WipeMethod};
+
+const MIB: u64 = 1024 * 1024;
+
+fn sampled(dir: &TempDir, percent: f64, seed: u64) -> WipeJob {
+    WipeJob::new(dir.path()).verify(VerifyPolicy::Sample(Sampling::new(percent, seed).unwrap()))
+}
+
+#[test]
+fn sample_covers_the_first_and_last_sections() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::new(40 * MIB + 4096);
+
+    let outcome = sampled(&dir, 10.0, 1).run_on(&target, |_| {}).unwrap();
+
+    let sample = outcome.sample.unwrap();
+    assert_eq!(outcome.verification, Verification::Passed);
+    assert_eq!(sample.regions.first().unwrap().start, 0);
+    assert_eq!(sample.regions.last().unwrap().end, 40 * MIB + 4096);
+    // 5 of 41 sections, the last one only 4 KiB.
+    assert_eq!(sample.bytes, 4 * MIB + 4096);
+    assert_eq!(sample.total, 40 * MIB + 4096);
+    assert_eq!(outcome.bytes_verified, sample.bytes);
+    assert!(sample.coverage() >= 10.0);
+}
+
+#[test]
+fn same_seed_draws_the_same_sample() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::new(32 * MIB);
+    let job = sampled(&dir, 10.0, 42);
+    let first = job.run_on(&target, |_| {}).unwrap().sample.unwrap();
+
+    let again = job.verify_on(&target, |_| {}).unwrap().sample.unwrap();
+    let other = sampled(&dir, 10.0, 43)
+        .verify_on(&target, |_| {})
+        .unwrap()
+        .sample
+        .unwrap();
+
+    assert_eq!(first.regions, again.regions);
+    assert_ne!(first.regions, other.regions);
+}
+
+#[test]
+fn only_the_sample_is_read_back() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::new(16 * MIB);
+    let job = sampled(&dir, 10.0, 9);
+    let sample = job.run_on(&target, |_| {}).unwrap().sample.unwrap();
+
+    let inside = sample.regions[0].start + 5;
+    let outside = (0..16 * MIB)
+        .step_by(MIB as usize)
+        .find(|&offset| !sample.regions.iter().any(|r| r.contains(&offset)))
+        .unwrap();
+
+    let byte = target.contents()[outside as usize];
+    target.poke(outside, &[!byte]);
+    job.verify_on(&target, |_| {}).unwrap();
+
+    let byte = target.contents()[inside as usize];
+    target.poke(inside, &[!byte]);
+    let error = job.verify_on(&target, |_| {}).unwrap_err();
+    assert!(
+        matches!(error, Error::VerifyMismatch { offset, .. } if offset == inside),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn full_verification_reads_every_pass() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::new(2 * MIB);
+
+    let outcome = WipeJob::new(dir.path())
+        .method(WipeMethod::standard("schneier").unwrap())
+        .verify(VerifyPolicy::EveryPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bytes_verified, 7 * 2 * MIB);
+    assert_eq!(outcome.sample, None);
+    let estimate = WipeJob::new(dir.path())
+        .method(WipeMethod::standard("schneier").unwrap())
+        .verify(VerifyPolicy::EveryPass)
+        .estimate(2 * MIB, MIB)
+        .unwrap();
+    assert_eq!(estimate.bytes_verified, 7 * 2 * MIB);
+}
+
+#[test]
+fn sample_percentage_must_be_in_range() {
+    assert!(Sampling::new(0.0, 1).is_err());
+    assert!(Sampling::new(100.5, 1).is_err());
+    assert!(Sampling::new(f64::NAN, 1).is_err());
+    assert_eq!(Sampling::new(100.0, 1).unwrap().percent(), 100.0);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::fake_system;
+use std::fs;
+use std::os::unix::fs::symlink;
+use wipers::{Error, Selector, SysRoot, Transport};
+
+fn names(sys: &SysRoot, selector: &str) -> Vec<String> {
+    let selector: Selector = selector.parse().unwrap();
+    sys.select(&selector, true)
+        .unwrap()
+        .into_iter()
+        .map(|d| d.name)
+        .collect()
+}
+
+#[test]
+fn parses_selectors_and_paths() {
+    assert_eq!(
+        "serial:WD-1234".parse::<Selector>().unwrap(),
+        Selector::Serial("WD-1234".into())
+    );
+    assert_eq!(
+        "model:\"ST4000*\"".parse::<Selector>().unwrap(),
+        Selector::Model("ST4000*".into())
+    );
+    assert_eq!(
+        "transport:USB".parse::<Selector>().unwrap(),
+        Selector::Transport(Transport::Usb)
+    );
+    assert_eq!(
+        "/dev/sdb".parse::<Selector>().unwrap(),
+        Selector::Path("/dev/sdb".into())
+    );
+    assert!(matches!(
+        "transport:floppy".parse::<Selector>(),
+        Err(Error::InvalidSelector(_))
+    ));
+    assert!("wwn:".parse::<Selector>().is_err());
+}
+
+#[test]
+fn resolves_single_drive_selectors() {
+    let dir = fake_system();
+    let by_id = dir.path().join("dev/disk/by-id");
+    fs::create_dir_all(&by_id).unwrap();
+    symlink("../../sdb", by_id.join("usb-SanDisk_Ultra_Fit_4C53-0:0")).unwrap();
+    let sys = SysRoot::new(dir.path());
+
+    assert_eq!(names(&sys, "serial:ZFN0ABCD"), ["sda"]);
+    assert_eq!(names(&sys, "wwn:0x5000C500B1234567"), ["sda"]);
+    assert_eq!(names(&sys, "wwn:5000c500b1234567"), ["sda"]);
+    assert_eq!(names(&sys, "by-id:usb-SanDisk_Ultra_Fit_4C53-0:0"), ["sdb"]);
+    assert_eq!(
+        names(&sys, "by-id:/dev/disk/by-id/usb-SanDisk_Ultra_Fit_4C53-0:0"),
+        ["sdb"]
+    );
+}
+
+#[test]
+fn resolves_group_selectors() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    assert_eq!(names(&sys, "model:ST4000*"), ["sda"]);
+    assert_eq!(names(&sys, "model:*"), ["sda", "sdb"]);
+    assert_eq!(names(&sys, "transport:usb"), ["sdb"]);
+}
+
+#[test]
+fn rejects_selectors_matching_nothing() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let selector: Selector = "transport:nvme".parse().unwrap();
+    assert!(matches!(
+        sys.select(&selector, true),
+        Err(Error::InvalidSelector(_))
+    ));
+}
+
+#[test]
+fn rejects_ambiguous_selectors() {
+    let dir = fake_system();
+    let sdb = fs::canonicalize(dir.path().join("sys/block/sdb")).unwrap();
+    fs::write(sdb.join("device/vpd_pg80"), "\0\0\0\x08ZFN0ABCD").unwrap();
+    let sys = SysRoot::new(dir.path());
+
+    let selector: Selector = "serial:ZFN0ABCD".parse().unwrap();
+    let err = sys.select(&selector, true).unwrap_err();
+    assert!(err.to_string().contains("ambiguous"));
+    assert!(err.to_string().contains("sda, sdb"));
+}
+
+#[test]
+fn group_selectors_matching_several_drives_need_opting_in() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let selector: Selector = "model:*".parse().unwrap();
+    let err = sys.select(&selector, false).unwrap_err();
+    assert!(err.to_string().contains("ambiguous"));
+    assert!(err.to_string().contains("sda, sdb"));
+
+    let selector: Selector = "model:ST4000*".parse().unwrap();
+    assert_eq!(sys.select(&selector, false).unwrap().len(), 1);
+}
@@ -0,0 +1,273 @@
+use std::fs;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tempfile::{NamedTempFile, TempDir};
+use wipers::{
+    BadSectorPolicy, BlockTarget, Error, FaultyTarget, JobStatus, MemoryTarget, RegularFile,
+    RunSecret, SparseMode, Verification, VerifyPolicy, WipeJob, WipeMethod,
+};
+
+const MIB: u64 = 1024 * 1024;
+
+/// A job named after a path that exists, so that failures are not mistaken
+/// for the target vanishing.
+fn job(dir: &TempDir) -> WipeJob {
+    WipeJob::new(dir.path())
+}
+
+fn junk(target: &MemoryTarget) {
+    let size = target.size() as usize;
+    let junk: Vec<u8> = (0..size).map(|i| (i % 251) as u8 | 1).collect();
+    target.poke(0, &junk);
+}
+
+#[test]
+fn memory_target_is_wiped_and_verified() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::new(3 * MIB + 512);
+    junk(&target);
+
+    let outcome = job(&dir)
+        .verify(VerifyPolicy::AfterLastPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bytes_written, 3 * MIB + 512);
+    assert_eq!(outcome.verification, Verification::Passed);
+    assert!(target.contents().iter().all(|&b| b == 0));
+}
+
+#[test]
+fn random_wipe_verifies_on_its_own_with_the_secret() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::with_sectors(2 * MIB, 512, 4096);
+    let secret = RunSecret::generate();
+    let job = job(&dir)
+        .method(WipeMethod::standard("random").unwrap())
+        .secret(secret.clone());
+
+    job.run_on(&target, |_| {}).unwrap();
+    job.verify_on(&target, |_| {}).unwrap();
+
+    let byte = target.contents()[(MIB + 7) as usize];
+    target.poke(MIB + 7, &[!byte]);
+    let mismatch = job.verify_on(&target, |_| {}).unwrap_err();
+    assert!(
+        matches!(mismatch, Error::VerifyMismatch { offset, .. } if offset == MIB + 7),
+        "{:?}",
+        mismatch
+    );
+}
+
+#[test]
+fn io_error_at_a_sector_fails_the_job() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(2 * MIB)).eio_at(3000);
+
+    let status = JobStatus::from(job(&dir).run_on(&target, |_| {}));
+
+    match status {
+        JobStatus::IoError(Error::Io { source, .. }) => {
+            assert_eq!(source.raw_os_error(), Some(libc::EIO))
+        }
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+    // Everything before the chunk holding the bad sector was written.
+    assert!(target.inner().contents()[..MIB as usize]
+        .iter()
+        .all(|&b| b == 0));
+}
+
+#[test]
+fn short_writes_still_cover_the_whole_target() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(MIB + 4096)).short_writes(1000);
+    junk(target.inner());
+
+    let outcome = job(&dir)
+        .method(WipeMethod::standard("dod").unwrap())
+        .verify(VerifyPolicy::AfterLastPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bytes_written, 3 * (MIB + 4096));
+    assert_eq!(outcome.verification, Verification::Passed);
+}
+
+#[test]
+fn disappearing_target_counts_as_vanished() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB)).disappear_after(2 * MIB);
+
+    let status = JobStatus::from(job(&dir).run_on(&target, |_| {}));
+
+    assert!(matches!(status, JobStatus::Vanished(_)), "{:?}", status);
+    assert!(target.inner().contents()[(3 * MIB) as usize..]
+        .iter()
+        .all(|&b| b == 0));
+}
+
+#[test]
+fn latency_spikes_slow_the_job_down_without_failing_it() {
+    let dir = TempDir::new().unwrap();
+    let target =
+        FaultyTarget::new(MemoryTarget::new(4 * MIB)).latency_spike(2, Duration::from_millis(20));
+
+    let started = Instant::now();
+    job(&dir).queue_depth(1).run_on(&target, |_| {}).unwrap();
+
+    // Four chunk writes one after the other, and a flush: two of them stall.
+    assert!(started.elapsed() >= Duration::from_millis(40));
+}
+
+#[test]
+fn dry_run_leaves_memory_target_untouched() {
+    let dir = TempDir::new().unwrap();
+    let target = MemoryTarget::new(MIB);
+    junk(&target);
+    let before = target.contents();
+
+    let outcome = job(&dir)
+        .dry_run(true)
+        .verify(VerifyPolicy::AfterLastPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert_eq!(outcome.verification, Verification::DryRun);
+    assert_eq!(target.contents(), before);
+}
+
+#[test]
+fn regular_file_target_wipes_only_data_extents() {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&[0xaa; 8192]).unwrap();
+    file.as_file().set_len(4 * MIB).unwrap();
+    let target = RegularFile::new(file.reopen().unwrap(), file.path()).unwrap();
+
+    let outcome = WipeJob::new(file.path())
+        .sparse(SparseMode::Extents)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert!(outcome.bytes_per_pass < 4 * MIB);
+    let contents = fs::read(file.path()).unwrap();
+    assert_eq!(contents.len() as u64, 4 * MIB);
+    assert!(contents.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn memory_target_discard_reads_back_zeros() {
+    let target = MemoryTarget::new(8192);
+    junk(&target);
+
+    target.discard(1024..4096).unwrap();
+
+    let contents = target.contents();
+    assert!(contents[1024..4096].iter().all(|&b| b == 0));
+    assert!(contents[..1024].iter().all(|&b| b != 0));
+    assert!(contents[4096..].iter().all(|&b| b != 0));
+}
+
+#[test]
+fn direct_reader_reads_unaligned_ranges() {
+    let mut file = NamedTempFile::new().unwrap();
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+    file.write_all(&data).unwrap();
+    let target = RegularFile::new(file.reopen().unwrap(), file.path()).unwrap();
+    target.invalidate_cache().unwrap();
+
+    // Filesystems without O_DIRECT, such as tmpfs, have no direct reader.
+    let Some(reader) = target.direct_reader().unwrap() else {
+        return;
+    };
+    let mut buf = vec![0u8; 9_000];
+    reader.read_exact_at(&mut buf, 13).unwrap();
+    assert_eq!(buf, data[13..9_013]);
+
+    let mut tail = vec![0u8; 100];
+    assert!(reader.read_exact_at(&mut tail, 9_950).is_err());
+    reader.read_exact_at(&mut tail[..50], 9_950).unwrap();
+    assert_eq!(tail[..50], data[9_950..]);
+}
+
+#[test]
+fn image_file_verifies_through_a_direct_reader() {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&[0xaa; 3 * 4096 + 100]).unwrap();
+    let target = RegularFile::new(file.reopen().unwrap(), file.path()).unwrap();
+    let job = WipeJob::new(file.path()).verify(VerifyPolicy::AfterLastPass);
+
+    let outcome = job.run_on(&target, |_| {}).unwrap();
+    assert_eq!(outcome.verification, Verification::Passed);
+
+    // A change made behind the wipe's back is seen, whatever is cached.
+    let other = file.reopen().unwrap();
+    std::os::unix::fs::FileExt::write_at(&other, &[1], 3 * 4096 + 99).unwrap();
+    let mismatch = job.verify_on(&target, |_| {}).unwrap_err();
+    assert!(
+        matches!(mismatch, Error::VerifyMismatch { offset, .. } if offset == 3 * 4096 + 99),
+        "{:?}",
+        mismatch
+    );
+}
+
+#[test]
+fn queued_writes_match_one_at_a_time() {
+    let dir = TempDir::new().unwrap();
+    let job = job(&dir)
+        .method(WipeMethod::standard("random").unwrap())
+        .secret(RunSecret::generate())
+        .block_size(96 * 1024);
+    let one = MemoryTarget::new(2 * MIB + 4096);
+    let queued = MemoryTarget::new(2 * MIB + 4096);
+
+    job.run_on(&one, |_| {}).unwrap();
+    let outcome = job.clone().queue_depth(5).run_on(&queued, |_| {}).unwrap();
+
+    assert_eq!(outcome.bytes_written, 2 * MIB + 4096);
+    assert!(one.contents() == queued.contents());
+}
+
+#[test]
+fn queued_writes_skip_bad_sectors() {
+    let dir = TempDir::new().unwrap();
+    let target = FaultyTarget::new(MemoryTarget::new(4 * MIB))
+        .eio_at(10)
+        .eio_at(4100);
+
+    let outcome = job(&dir)
+        .queue_depth(4)
+        .on_bad_sector(BadSectorPolicy::Skip {
+            retries: 0,
+            backoff: Duration::ZERO,
+        })
+        .verify(VerifyPolicy::AfterLastPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+
+    assert_eq!(outcome.bad_sectors, vec![10..11, 4100..4101]);
+    assert_eq!(outcome.bytes_written, 4 * MIB - 1024);
+}
+
+#[test]
+fn image_file_is_written_directly_where_supported() {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&[0xaa; 2 * MIB as usize + 100]).unwrap();
+    let target = RegularFile::new(file.reopen().unwrap(), file.path()).unwrap();
+    let direct = target.direct_writer().unwrap().is_some();
+
+    let outcome = WipeJob::new(file.path())
+        .queue_depth(3)
+        .verify(VerifyPolicy::AfterLastPass)
+        .run_on(&target, |_| {})
+        .unwrap();
+    assert_eq!(outcome.direct_io, direct);
+    // The unaligned tail is written too.
+    assert!(fs::read(file.path()).unwrap().iter().all(|&b| b == 0));
+
+    let outcome = WipeJob::new(file.path())
+        .direct_io(false)
+        .run_on(&target, |_| {})
+        .unwrap();
+    assert!(!outcome.direct_io);
+}
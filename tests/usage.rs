@@ -0,0 +1,117 @@
+mod common;
+
+use common::{block, fake_system, write};
+use std::fs;
+use std::os::unix::fs::symlink;
+use wipers::{Blocker, DevId, Reason, SysRoot};
+
+#[test]
+fn reports_partition_mounts_swap_and_stacked_devices() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let blockers = sys.blockers(DevId { major: 8, minor: 0 }).unwrap();
+
+    assert_eq!(
+        blockers,
+        vec![
+            Blocker {
+                device: "dm-0 (vg-root)".into(),
+                reason: Reason::Mounted {
+                    mount_point: "/".into(),
+                    fs_type: "ext4".into(),
+                },
+            },
+            Blocker {
+                device: "sda1".into(),
+                reason: Reason::Mounted {
+                    mount_point: "/boot".into(),
+                    fs_type: "vfat".into(),
+                },
+            },
+            Blocker {
+                device: "sda2".into(),
+                reason: Reason::Swap,
+            },
+            Blocker {
+                device: "sda3".into(),
+                reason: Reason::HeldBy {
+                    holder: "dm-0 (vg-root)".into(),
+                },
+            },
+        ]
+    );
+}
+
+#[test]
+fn similar_names_and_paths_do_not_match() {
+    let dir = fake_system();
+    let sys = SysRoot::new(dir.path());
+
+    let blockers = sys
+        .blockers(DevId {
+            major: 8,
+            minor: 16,
+        })
+        .unwrap();
+
+    assert_eq!(blockers.len(), 1);
+    assert_eq!(blockers[0].device, "sdb1");
+    assert_eq!(
+        blockers[0].reason,
+        Reason::Mounted {
+            mount_point: "/mnt/dev/sda backup".into(),
+            fs_type: "ext4".into(),
+        }
+    );
+}
+
+#[test]
+fn idle_disk_has_no_blockers() {
+    let dir = fake_system();
+    let root = dir.path();
+    block(root, "sdc", "8:32", None);
+    let sys = SysRoot::new(root);
+
+    assert!(sys
+        .blockers(DevId {
+            major: 8,
+            minor: 32
+        })
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn resolves_mounts_without_a_device_number_through_dev() {
+    let dir = fake_system();
+    let root = dir.path();
+    block(root, "sdc", "8:32", None);
+    // btrfs reports an anonymous device number; only its source names sdc.
+    fs::create_dir_all(root.join("dev/disk/by-label")).unwrap();
+    symlink("../../sdc", root.join("dev/disk/by-label/data")).unwrap();
+    write(
+        root,
+        "proc/self/mountinfo",
+        "22 1 0:35 / /data rw,relatime - btrfs /dev/disk/by-label/data rw\n",
+    );
+    let sys = SysRoot::new(root);
+
+    let blockers = sys
+        .blockers(DevId {
+            major: 8,
+            minor: 32,
+        })
+        .unwrap();
+
+    assert_eq!(
+        blockers,
+        vec![Blocker {
+            device: "sdc".into(),
+            reason: Reason::Mounted {
+                mount_point: "/data".into(),
+                fs_type: "btrfs".into(),
+            },
+        }]
+    );
+}